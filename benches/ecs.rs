@@ -0,0 +1,164 @@
+//! Compares the iteration throughput of the sparse set component storage used
+//! by the `World` against the previous layout, where every component was boxed
+//! and stored in a `Vec<Option<Box<dyn Any>>>` indexed by entity ID.
+//!
+//! Run with `cargo bench --bench ecs`.
+
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use peacock::ecs::{Component, World};
+use peacock::Vector2f;
+
+const ENTITY_COUNTS: [usize; 3] = [1_000, 10_000, 50_000];
+const ITERATIONS: u32 = 200;
+
+struct Position(Vector2f);
+
+impl Component for Position {}
+
+struct Velocity(Vector2f);
+
+impl Component for Velocity {}
+
+/// Every third entity is an orc, so that the storages are not all the same size.
+struct Orc;
+
+impl Component for Orc {}
+
+/// The component layout used by the `World` before it switched to sparse sets.
+struct BoxedWorld {
+    entities: Vec<usize>,
+    components: HashMap<TypeId, Vec<Option<Box<dyn Any>>>>,
+}
+
+impl BoxedWorld {
+    const DEFAULT_STORAGE_CAPACITY: usize = 256;
+
+    fn new() -> Self {
+        Self {
+            entities: Vec::new(),
+            components: HashMap::new(),
+        }
+    }
+
+    fn attach<T: Component>(&mut self, id: usize, component: T) {
+        let components = self
+            .components
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Vec::with_capacity(Self::DEFAULT_STORAGE_CAPACITY));
+
+        if components.len() <= id {
+            components.resize_with(
+                components.len() + Self::DEFAULT_STORAGE_CAPACITY,
+                Default::default,
+            );
+        }
+
+        components[id] = Some(Box::new(component));
+    }
+
+    fn get<T: Component>(&self, id: usize) -> Option<&T> {
+        self.components.get(&TypeId::of::<T>())?[id]
+            .as_ref()
+            .and_then(|component| component.downcast_ref::<T>())
+    }
+
+    fn get_mut<T: Component>(&mut self, id: usize) -> Option<&mut T> {
+        self.components.get_mut(&TypeId::of::<T>())?[id]
+            .as_mut()
+            .and_then(|component| component.downcast_mut::<T>())
+    }
+}
+
+fn velocity(index: usize) -> Velocity {
+    Velocity(Vector2f::new(index as f32 * 0.01, 1.0))
+}
+
+fn bench_boxed(entity_count: usize) -> Duration {
+    let mut world = BoxedWorld::new();
+
+    for index in 0..entity_count {
+        let id = index + 1;
+        world.attach(id, Position(Vector2f::ZERO));
+        world.attach(id, velocity(index));
+        if index % 3 == 0 {
+            world.attach(id, Orc);
+        }
+        world.entities.push(id);
+    }
+
+    let start = Instant::now();
+
+    for _ in 0..ITERATIONS {
+        for index in 0..world.entities.len() {
+            let id = world.entities[index];
+
+            let velocity = match world.get::<Velocity>(id) {
+                Some(velocity) => velocity.0,
+                None => continue,
+            };
+
+            if let Some(position) = world.get_mut::<Position>(id) {
+                position.0 += velocity;
+            }
+        }
+    }
+
+    black_box(&world);
+    start.elapsed()
+}
+
+fn bench_sparse_set(entity_count: usize) -> Duration {
+    let mut world = World::new();
+
+    for index in 0..entity_count {
+        let builder = world
+            .create_entity()
+            .with(Position(Vector2f::ZERO))
+            .with(velocity(index));
+
+        if index % 3 == 0 {
+            builder.with(Orc).build();
+        } else {
+            builder.build();
+        }
+    }
+
+    let start = Instant::now();
+
+    for _ in 0..ITERATIONS {
+        for (_, (position, velocity)) in world.query::<(&mut Position, &Velocity)>().unwrap() {
+            position.0 += velocity.0;
+        }
+    }
+
+    black_box(&world);
+    start.elapsed()
+}
+
+fn throughput(entity_count: usize, elapsed: Duration) -> f64 {
+    (entity_count as f64 * f64::from(ITERATIONS)) / elapsed.as_secs_f64() / 1e6
+}
+
+fn main() {
+    println!(
+        "{:>10} | {:>25} | {:>25} | {:>8}",
+        "entities", "boxed (M entities/s)", "sparse set (M entities/s)", "speedup"
+    );
+
+    for &entity_count in &ENTITY_COUNTS {
+        let boxed = bench_boxed(entity_count);
+        let sparse_set = bench_sparse_set(entity_count);
+
+        println!(
+            "{:>10} | {:>25.1} | {:>25.1} | {:>7.1}x",
+            entity_count,
+            throughput(entity_count, boxed),
+            throughput(entity_count, sparse_set),
+            boxed.as_secs_f64() / sparse_set.as_secs_f64()
+        );
+    }
+}
@@ -1,6 +1,6 @@
 use peacock::graphics::{self, Animation, DrawAnimationParams, Image, Rectangle, View};
 use peacock::window;
-use peacock::{ContextBuilder, Result, State};
+use peacock::{ContextBuilder, Result, State, Transition};
 
 type Context = peacock::Context<()>;
 
@@ -30,10 +30,10 @@ impl AnimationExample {
 impl State for AnimationExample {
     type Context = ();
 
-    fn update(&mut self, _ctx: &mut Context) -> Result<()> {
+    fn update(&mut self, _ctx: &mut Context) -> Result<Transition<Self::Context>> {
         self.animation.tick();
 
-        Ok(())
+        Ok(Transition::None)
     }
 
     fn draw(&mut self, ctx: &mut Context, _dt: f64) -> Result<()> {
@@ -1,5 +1,5 @@
 use peacock::graphics::{self, Color, DrawImageParams, Image};
-use peacock::{ContextBuilder, Result, State};
+use peacock::{ContextBuilder, Result, State, Transition};
 
 type Context = peacock::Context<()>;
 
@@ -175,8 +175,8 @@ impl ColorPaletteExample {
 impl State for ColorPaletteExample {
     type Context = ();
 
-    fn update(&mut self, _ctx: &mut Context) -> Result<()> {
-        Ok(())
+    fn update(&mut self, _ctx: &mut Context) -> Result<Transition<Self::Context>> {
+        Ok(Transition::None)
     }
 
     fn draw(&mut self, ctx: &mut Context, _dt: f64) -> Result<()> {
@@ -1,18 +1,11 @@
-use peacock::ecs::{self, Component, Entity};
+use peacock::ecs::{self, Component, GlobalTransform, Name, Transform};
 use peacock::graphics::{self, DrawImageParams, Image, Rectangle};
 use peacock::input::{self, Key};
 use peacock::Result;
-use peacock::{ContextBuilder, State, Vector2f};
+use peacock::{ContextBuilder, State, Transition, Vector2f};
 
 type Context = peacock::Context<()>;
 
-#[derive(Debug)]
-struct Transform {
-    pub position: Vector2f,
-}
-
-impl Component for Transform {}
-
 #[derive(Debug)]
 struct StaticSprite {
     pub source: Rectangle<i32>,
@@ -22,7 +15,6 @@ impl Component for StaticSprite {}
 
 struct EcsExample {
     sprite_sheet: Image,
-    player: Entity,
 }
 
 impl EcsExample {
@@ -30,24 +22,32 @@ impl EcsExample {
         let sprite_sheet = Image::from_file(ctx, "examples/res/0x72_dungeon_ii.png")?;
 
         let player = ecs::create_entity(ctx)
+            .with(Name::new("player"))
             .with(Transform {
-                position: Vector2f::new(0.0, 0.0),
+                scale: Vector2f::new(8.0, 8.0),
+                ..Default::default()
             })
             .with(StaticSprite {
                 source: Rectangle::<i32>::new(128, 76, 15, 20),
             })
             .build();
 
-        Ok(Self {
-            sprite_sheet,
-            player,
-        })
+        let weapon = ecs::create_entity(ctx)
+            .with(Transform::from_position(Vector2f::new(12.0, 6.0)))
+            .with(StaticSprite {
+                source: Rectangle::<i32>::new(293, 18, 6, 13),
+            })
+            .build();
+
+        ecs::set_parent(ctx, weapon, player)?;
+
+        Ok(Self { sprite_sheet })
     }
 }
 
 impl State for EcsExample {
     type Context = ();
-    fn update(&mut self, ctx: &mut Context) -> Result<()> {
+    fn update(&mut self, ctx: &mut Context) -> Result<Transition<Self::Context>> {
         let direction = {
             let mut direction = Vector2f::ZERO;
 
@@ -74,33 +74,32 @@ impl State for EcsExample {
             }
         };
 
-        if let Some(transform) = ecs::get_component_mut::<_, Transform>(ctx, self.player) {
+        let player = ecs::find_by_name(ctx, "player");
+        if let Some(transform) =
+            player.and_then(|player| ecs::get_component_mut::<_, Transform>(ctx, player))
+        {
             let speed = 10.0;
 
             transform.position += direction * speed;
         }
 
-        Ok(())
+        Ok(Transition::None)
     }
 
     fn draw(&mut self, ctx: &mut Context, _dt: f64) -> Result<()> {
-        for entity in ecs::entities(ctx) {
-            let transform = ecs::get_component::<_, Transform>(ctx, entity);
-            let static_sprite = ecs::get_component::<_, StaticSprite>(ctx, entity);
-
-            match (transform, static_sprite) {
-                (Some(transform), Some(static_sprite)) => {
-                    let draw_params = DrawImageParams {
-                        position: transform.position,
-                        clip_rect: Some(static_sprite.source),
-                        scale: Some(Vector2f::new(8.0, 8.0)),
-                        ..Default::default()
-                    };
-
-                    graphics::draw(ctx, &self.sprite_sheet, &draw_params)?;
-                }
-                _ => {}
+        let sprites = ecs::query::<_, (&GlobalTransform, &StaticSprite)>(ctx)?
+            .map(|(_, (transform, static_sprite))| (*transform, static_sprite.source))
+            .collect::<Vec<_>>();
+
+        for (transform, source) in sprites {
+            let draw_params = DrawImageParams {
+                position: transform.position,
+                clip_rect: Some(source),
+                scale: Some(transform.scale),
+                ..Default::default()
             };
+
+            graphics::draw(ctx, &self.sprite_sheet, &draw_params)?;
         }
 
         Ok(())
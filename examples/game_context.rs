@@ -1,5 +1,5 @@
 use peacock::graphics::{self, DrawTextParams, Font, Text};
-use peacock::{ContextBuilder, Result, State, Vector2f};
+use peacock::{ContextBuilder, Result, State, Transition, Vector2f};
 
 type Context = peacock::Context<GameContext>;
 
@@ -30,8 +30,8 @@ impl GameContextExample {
 impl State for GameContextExample {
     type Context = GameContext;
 
-    fn update(&mut self, _ctx: &mut Context) -> Result<()> {
-        Ok(())
+    fn update(&mut self, _ctx: &mut Context) -> Result<Transition<Self::Context>> {
+        Ok(Transition::None)
     }
 
     fn draw(&mut self, ctx: &mut Context, _dt: f64) -> Result<()> {
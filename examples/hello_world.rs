@@ -1,5 +1,5 @@
 use peacock::Result;
-use peacock::{ContextBuilder, State};
+use peacock::{ContextBuilder, State, Transition};
 
 type Context = peacock::Context<()>;
 
@@ -8,8 +8,8 @@ struct HelloWorldExample;
 impl State for HelloWorldExample {
     type Context = ();
 
-    fn update(&mut self, _ctx: &mut Context) -> Result<()> {
-        Ok(())
+    fn update(&mut self, _ctx: &mut Context) -> Result<Transition<Self::Context>> {
+        Ok(Transition::None)
     }
 
     fn draw(&mut self, _ctx: &mut Context, _dt: f64) -> Result<()> {
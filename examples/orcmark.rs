@@ -4,8 +4,9 @@ use rand::{self, Rng};
 use peacock::graphics::{self, DrawImageParams, Image, Rectangle};
 use peacock::input::{self, Key};
 use peacock::time;
+use peacock::timer::{self, Interval, Timer, TimerHandle};
 use peacock::window;
-use peacock::{ContextBuilder, Result, State, Vector2f};
+use peacock::{ContextBuilder, Result, State, Transition, Vector2f};
 
 type Context = peacock::Context<()>;
 
@@ -23,6 +24,8 @@ const ORC_SHAMAN_HEIGHT: i32 = 15;
 
 const GRAVITY: f32 = 0.5;
 
+const SPAWN_COOLDOWN: Interval = Interval::Ticks(10);
+
 enum OrcKind {
     Grunt,
     Shaman,
@@ -56,7 +59,7 @@ struct OrcMarkExample {
     rng: ThreadRng,
     sprite_sheet: Image,
     orcs: Vec<Orc>,
-    spawn_timer: i32,
+    spawn_cooldown: Option<TimerHandle>,
 }
 
 impl OrcMarkExample {
@@ -73,7 +76,7 @@ impl OrcMarkExample {
             rng,
             sprite_sheet,
             orcs,
-            spawn_timer: 0,
+            spawn_cooldown: None,
         })
     }
 }
@@ -81,17 +84,17 @@ impl OrcMarkExample {
 impl State for OrcMarkExample {
     type Context = ();
 
-    fn update(&mut self, ctx: &mut Context) -> Result<()> {
-        if self.spawn_timer > 0 {
-            self.spawn_timer -= 1;
-        }
+    fn update(&mut self, ctx: &mut Context) -> Result<Transition<Self::Context>> {
+        let is_cooling_down = self
+            .spawn_cooldown
+            .is_some_and(|cooldown| timer::is_active(ctx, cooldown));
 
-        if input::is_key_down(ctx, Key::Space) && self.spawn_timer == 0 {
+        if input::is_key_down(ctx, Key::Space) && !is_cooling_down {
             for _ in 0..INITIAL_ORCS {
                 self.orcs.push(Orc::new(&mut self.rng));
             }
 
-            self.spawn_timer = 10;
+            self.spawn_cooldown = Some(timer::start(ctx, Timer::once(SPAWN_COOLDOWN))?);
         }
 
         for orc in &mut self.orcs {
@@ -127,7 +130,7 @@ impl State for OrcMarkExample {
             }
         }
 
-        Ok(())
+        Ok(Transition::None)
     }
 
     fn draw(&mut self, ctx: &mut Context, _dt: f64) -> Result<()> {
@@ -1,6 +1,6 @@
 use peacock::graphics::{self, DrawTextParams, Font, Text};
 use peacock::Result;
-use peacock::{ContextBuilder, State, Vector2f};
+use peacock::{ContextBuilder, State, Transition, Vector2f};
 
 type Context = peacock::Context<()>;
 
@@ -31,8 +31,8 @@ impl TextExample {
 impl State for TextExample {
     type Context = ();
 
-    fn update(&mut self, _ctx: &mut Context) -> Result<()> {
-        Ok(())
+    fn update(&mut self, _ctx: &mut Context) -> Result<Transition<Self::Context>> {
+        Ok(Transition::None)
     }
 
     fn draw(&mut self, ctx: &mut Context, _dt: f64) -> Result<()> {
@@ -1,18 +1,21 @@
-use std::time::{Duration, Instant};
+use std::time::Instant;
 
 use lazy_static::*;
 use sdl2::event::Event;
-use sdl2::render::Canvas;
+use sdl2::image::LoadSurface;
+use sdl2::keyboard::Keycode;
+use sdl2::surface::Surface;
 use sdl2::ttf::Sdl2TtfContext;
-use sdl2::video::Window;
 use sdl2::Sdl;
 
-use crate::ecs::World;
+use crate::ecs::{Schedule, Stage, World, Worlds};
 use crate::error::{AnyhowContext, Result, Sdl2Error};
-use crate::graphics::{self, Color, GraphicsContext};
+use crate::graphics::{self, Canvas, Color, GraphicsContext};
 use crate::input::{self, KeyboardContext, MouseContext};
-use crate::time;
-use crate::{FpsTracker, State};
+use crate::time::{self, TimeContext, Timestep};
+use crate::timer::Timers;
+use crate::window::Fullscreen;
+use crate::{FpsTracker, State, StateStack, Vector2i, Vector2u};
 
 lazy_static! {
     pub(crate) static ref SDL_TTF_CONTEXT: Sdl2TtfContext = sdl2::ttf::init().unwrap();
@@ -20,14 +23,19 @@ lazy_static! {
 
 pub struct Context<G> {
     pub(crate) sdl_context: Sdl,
-    pub(crate) canvas: Canvas<Window>,
+    pub(crate) canvas: Canvas,
     is_running: bool,
-    tick_rate: Duration,
+    quit_on_escape: bool,
+    pub(crate) time: TimeContext,
+    pub(crate) timers: Timers<G>,
     pub(crate) fps_tracker: FpsTracker,
     pub(crate) world: World,
+    pub(crate) worlds: Worlds,
+    pub(crate) schedule: Schedule<G>,
     pub(crate) graphics: GraphicsContext,
     pub(crate) keyboard: KeyboardContext,
     pub(crate) mouse: MouseContext,
+    states: StateStack<G>,
     game: G,
 }
 
@@ -52,15 +60,35 @@ impl<G> Context<G> {
     }
 
     /// Runs the context using the provided game state.
+    ///
+    /// The game runs until the last state is popped off of the stack, a state
+    /// returns [`Transition::Quit`](crate::Transition::Quit), or the window is
+    /// closed.
     pub fn run<S>(&mut self, state: &mut S) -> Result<()>
     where
         S: State<Context = G>,
     {
+        let mut states = StateStack::new();
+
+        let result = self.run_states(&mut states, state);
+        let exited = states.clear(self, state);
+
+        result.and(exited)
+    }
+
+    fn run_states(
+        &mut self,
+        states: &mut StateStack<G>,
+        state: &mut dyn State<Context = G>,
+    ) -> Result<()> {
         let mut last_time = Instant::now();
-        let mut lag = Duration::from_secs(0);
 
         self.is_running = true;
 
+        self.schedule.initialize()?;
+
+        states.start(self, state)?;
+
         let mut event_pump = self
             .sdl_context
             .event_pump()
@@ -68,10 +96,9 @@ impl<G> Context<G> {
             .context("Failed to obtain the SDL2 event pump")?;
 
         while self.is_running {
-            let current_time = Instant::now();
-            let elapsed_time = current_time - last_time;
-            last_time = current_time;
-            lag += elapsed_time;
+            let frame_start = Instant::now();
+            let elapsed_time = frame_start - last_time;
+            last_time = frame_start;
 
             self.fps_tracker.tick(elapsed_time);
 
@@ -85,28 +112,81 @@ impl<G> Context<G> {
                 }
             }
 
-            while lag >= self.tick_rate {
-                if let Err(err) = state.update(self) {
+            let updates = self.time.start_frame(elapsed_time);
+            for _ in 0..updates {
+                if !self.is_running {
+                    break;
+                }
+
+                if let Err(err) = self.update(states, state) {
                     self.is_running = false;
                     return Err(err);
                 }
-
-                input::cleanup_after_state_update(self);
-                lag -= self.tick_rate;
             }
 
-            let dt = time::duration_to_f64(lag) / time::duration_to_f64(self.tick_rate);
+            let dt = self.time.alpha();
 
-            graphics::clear(self, Color::CADET_BLUE);
-
-            if let Err(err) = state.draw(self, dt) {
+            if let Err(err) = self.draw(states, state, dt) {
                 self.is_running = false;
                 return Err(err);
             }
 
-            self.canvas.present();
+            match self.time.time_left_in_frame(frame_start.elapsed()) {
+                Some(time_left) => std::thread::sleep(time_left),
+                None => std::thread::yield_now(),
+            }
+        }
 
-            std::thread::yield_now();
+        Ok(())
+    }
+
+    /// Advances the game by exactly `updates` fixed updates using the provided
+    /// game state, without waiting for any real time to pass, and then draws
+    /// a single frame if `draw` is `true`.
+    ///
+    /// This is meant for driving a [headless](ContextBuilder::headless)
+    /// context from tests, so that the game can be checked frame by frame.
+    /// The state is entered on the first step, and the states that it pushes
+    /// on top of itself are kept from one step to the next. Once the game has
+    /// quit, further steps do nothing.
+    ///
+    /// The updates are run even if the game is [paused](time::pause), and each
+    /// one advances the game by the tick rate, regardless of the time scale.
+    pub fn step<S>(&mut self, state: &mut S, updates: u32, draw: bool) -> Result<()>
+    where
+        S: State<Context = G>,
+    {
+        let mut states = std::mem::take(&mut self.states);
+        let result = self.step_states(&mut states, state, updates, draw);
+        self.states = states;
+
+        result
+    }
+
+    fn step_states(
+        &mut self,
+        states: &mut StateStack<G>,
+        state: &mut dyn State<Context = G>,
+        updates: u32,
+        draw: bool,
+    ) -> Result<()> {
+        if !states.is_started() {
+            self.is_running = true;
+            self.schedule.initialize()?;
+            states.start(self, state)?;
+        }
+
+        for _ in 0..updates {
+            if states.is_empty() {
+                return Ok(());
+            }
+
+            self.time.start_step();
+            self.update(states, state)?;
+        }
+
+        if draw && !states.is_empty() {
+            self.draw(states, state, 0.0)?;
         }
 
         Ok(())
@@ -132,9 +212,104 @@ impl<G> Context<G> {
         self.run(&mut state)
     }
 
+    /// Runs a single fixed update.
+    fn update(
+        &mut self,
+        states: &mut StateStack<G>,
+        state: &mut dyn State<Context = G>,
+    ) -> Result<()> {
+        self.world.poll_prefab_files(Instant::now());
+
+        self.run_timers()?;
+        self.run_stage(Stage::PreUpdate)?;
+
+        states.update(self, state)?;
+        if states.is_empty() {
+            self.is_running = false;
+        }
+        self.world.apply_commands()?;
+
+        self.run_stage(Stage::Update)?;
+        self.run_stage(Stage::PostUpdate)?;
+
+        self.world.propagate_transforms();
+        self.world.update_spatial_index();
+
+        input::cleanup_after_state_update(self);
+        self.world.clear_trackers();
+        self.time.finish_update();
+
+        Ok(())
+    }
+
+    /// Advances the timers and runs the callbacks of the ones that fired.
+    fn run_timers(&mut self) -> Result<()> {
+        for (handle, fired) in self.timers.advance(self.time.delta()) {
+            // The timer may have been cancelled by an earlier callback.
+            let Some(mut callback) = self.timers.take_callback(handle) else {
+                continue;
+            };
+
+            let mut result = Ok(());
+            for _ in 0..fired {
+                result = callback(self);
+                if result.is_err() {
+                    break;
+                }
+            }
+
+            self.timers.restore_callback(handle, callback);
+            result?;
+        }
+
+        self.world.apply_commands()?;
+
+        Ok(())
+    }
+
+    /// Draws a single frame, where `dt` is how far along the game is between
+    /// the last fixed update and the next one.
+    fn draw(
+        &mut self,
+        states: &mut StateStack<G>,
+        state: &mut dyn State<Context = G>,
+        dt: f64,
+    ) -> Result<()> {
+        graphics::clear(self, Color::CADET_BLUE);
+
+        self.run_stage(Stage::Render)?;
+        states.draw(self, state, dt)?;
+
+        self.canvas.present();
+        self.time.finish_frame();
+
+        Ok(())
+    }
+
+    /// Runs all of the systems in the given stage and applies the commands
+    /// that they recorded.
+    fn run_stage(&mut self, stage: Stage) -> Result<()> {
+        let mut schedule = std::mem::take(&mut self.schedule);
+        let result = schedule.run_stage(stage, self);
+
+        // Any systems added, or mode set, while the stage was running will have
+        // gone to the empty schedule we left in its place.
+        let added_systems = std::mem::replace(&mut self.schedule, schedule);
+        self.schedule.append(added_systems);
+
+        result?;
+        self.world.apply_commands()?;
+
+        Ok(())
+    }
+
     fn handle_event(&mut self, event: Event) -> Result<Event> {
         match event {
             Event::Quit { .. } => self.is_running = false,
+            Event::KeyDown {
+                keycode: Some(Keycode::Escape),
+                ..
+            } if self.quit_on_escape => self.is_running = false,
             _ => {}
         }
 
@@ -149,8 +324,19 @@ pub struct ContextBuilder<'a> {
     height: u32,
     vsync: bool,
     tick_rate: f64,
-    fullscreen: bool,
+    target_frame_rate: Option<f64>,
+    max_updates_per_frame: u32,
+    timestep: Timestep,
+    fullscreen: Fullscreen,
+    resizable: bool,
+    borderless: bool,
+    high_dpi: bool,
+    min_size: Option<Vector2u>,
+    max_size: Option<Vector2u>,
+    icon: Option<&'a str>,
+    position: Option<Vector2i>,
     quit_on_escape: bool,
+    headless: bool,
 }
 
 impl<'a> ContextBuilder<'a> {
@@ -173,59 +359,233 @@ impl<'a> ContextBuilder<'a> {
         self
     }
 
+    /// Limits the game loop to the given number of frames per second, sleeping
+    /// for the rest of each frame. The frame rate is not limited by default.
+    pub fn target_frame_rate(&mut self, target_frame_rate: Option<f64>) -> &mut Self {
+        self.target_frame_rate = target_frame_rate;
+        self
+    }
+
+    /// Sets the maximum number of fixed updates that are run in a single frame,
+    /// beyond which the lag is dropped.
+    pub fn max_updates_per_frame(&mut self, max_updates_per_frame: u32) -> &mut Self {
+        self.max_updates_per_frame = max_updates_per_frame;
+        self
+    }
+
+    /// Sets how the game loop advances the game between frames.
+    pub fn timestep(&mut self, timestep: Timestep) -> &mut Self {
+        self.timestep = timestep;
+        self
+    }
+
     pub fn fullscreen(&mut self, fullscreen: bool) -> &mut Self {
-        self.fullscreen = fullscreen;
+        self.fullscreen = if fullscreen {
+            Fullscreen::Exclusive
+        } else {
+            Fullscreen::Off
+        };
+        self
+    }
+
+    /// Sets whether the window is fullscreen at the current resolution of the
+    /// desktop, rather than changing the video mode of the display.
+    pub fn desktop_fullscreen(&mut self, desktop_fullscreen: bool) -> &mut Self {
+        self.fullscreen = if desktop_fullscreen {
+            Fullscreen::Desktop
+        } else {
+            Fullscreen::Off
+        };
+        self
+    }
+
+    /// Sets whether the window can be resized by the user.
+    pub fn resizable(&mut self, resizable: bool) -> &mut Self {
+        self.resizable = resizable;
+        self
+    }
+
+    /// Sets whether the window is shown without decorations.
+    pub fn borderless(&mut self, borderless: bool) -> &mut Self {
+        self.borderless = borderless;
+        self
+    }
+
+    /// Sets whether the window is created in high-DPI mode, where supported.
+    pub fn high_dpi(&mut self, high_dpi: bool) -> &mut Self {
+        self.high_dpi = high_dpi;
+        self
+    }
+
+    /// Sets the size that the window cannot be resized below.
+    pub fn min_size(&mut self, width: u32, height: u32) -> &mut Self {
+        self.min_size = Some(Vector2u::new(width, height));
+        self
+    }
+
+    /// Sets the size that the window cannot be resized above.
+    pub fn max_size(&mut self, width: u32, height: u32) -> &mut Self {
+        self.max_size = Some(Vector2u::new(width, height));
+        self
+    }
+
+    /// Sets the window icon to the image in the given file.
+    pub fn icon(&mut self, filename: &'a str) -> &mut Self {
+        self.icon = Some(filename);
         self
     }
 
+    /// Sets the initial position of the window on the screen.
+    ///
+    /// The window is centered on the screen by default.
+    pub fn position(&mut self, x: i32, y: i32) -> &mut Self {
+        self.position = Some(Vector2i::new(x, y));
+        self
+    }
+
+    /// Sets whether pressing Escape quits the game.
+    pub fn quit_on_escape(&mut self, quit_on_escape: bool) -> &mut Self {
+        self.quit_on_escape = quit_on_escape;
+        self
+    }
+
+    /// Builds a context without a window, which renders to an offscreen
+    /// surface of the configured size instead.
+    ///
+    /// A headless context can run on a machine without a display, such as a
+    /// build server, and is usually driven with [`Context::step`].
+    pub fn headless(&mut self) -> &mut Self {
+        self.headless = true;
+        self
+    }
+
+    /// Builds the context, using the provided function to create the game
+    /// context.
+    ///
+    /// The function is given a context without a game context, so exclusive
+    /// systems and timers with callbacks cannot be added from it, and should be
+    /// added to the built context instead. Returns an error if one was added.
+    /// Parallel systems and timers without callbacks are kept.
+    ///
+    /// Also returns an error if the tick rate, target frame rate or maximum
+    /// updates per frame are invalid, as described by their setters in the
+    /// [`time`](crate::time) module.
     pub fn build<G, F>(&self, build_game_ctx: F) -> Result<Context<G>>
     where
         F: FnOnce(&mut Context<()>) -> Result<G>,
     {
+        let mut time = TimeContext::new(time::tick_rate_to_duration(self.tick_rate)?);
+        time.set_target_frame_rate(self.target_frame_rate)?;
+        time.set_max_updates_per_frame(self.max_updates_per_frame)?;
+        time.timestep = self.timestep;
+
         let sdl_context = sdl2::init()
             .map_err(Sdl2Error::ErrorMessage)
             .context("Failed to initialize SDL2 context")?;
-        let video_subsystem = sdl_context
-            .video()
-            .map_err(Sdl2Error::ErrorMessage)
-            .context("Failed to initialize SDL2 video subsystem")?;
+        let canvas = if self.headless {
+            Canvas::headless(self.width, self.height)
+                .map_err(Sdl2Error::ErrorMessage)
+                .context("Failed to build headless SDL2 canvas")?
+        } else {
+            let video_subsystem = sdl_context
+                .video()
+                .map_err(Sdl2Error::ErrorMessage)
+                .context("Failed to initialize SDL2 video subsystem")?;
+
+            let mut window_builder = video_subsystem.window(self.title, self.width, self.height);
+            match self.position {
+                Some(position) => window_builder.position(position.x, position.y),
+                None => window_builder.position_centered(),
+            };
+            match self.fullscreen {
+                Fullscreen::Off => {}
+                Fullscreen::Exclusive => {
+                    window_builder.fullscreen();
+                }
+                Fullscreen::Desktop => {
+                    window_builder.fullscreen_desktop();
+                }
+            }
+            if self.resizable {
+                window_builder.resizable();
+            }
+            if self.borderless {
+                window_builder.borderless();
+            }
+            if self.high_dpi {
+                window_builder.allow_highdpi();
+            }
+
+            let mut window = window_builder
+                .build()
+                .context("Failed to build SDL2 window")?;
 
-        let window = video_subsystem
-            .window(self.title, self.width, self.height)
-            .position_centered()
-            .build()
-            .context("Failed to build SDL2 window")?;
+            if let Some(min_size) = self.min_size {
+                window
+                    .set_minimum_size(min_size.x, min_size.y)
+                    .context("Failed to set minimum window size")?;
+            }
+            if let Some(max_size) = self.max_size {
+                window
+                    .set_maximum_size(max_size.x, max_size.y)
+                    .context("Failed to set maximum window size")?;
+            }
+            if let Some(icon) = self.icon {
+                let icon = Surface::from_file(icon)
+                    .map_err(Sdl2Error::ErrorMessage)
+                    .with_context(|| format!("Failed to load window icon from file: {}", icon))?;
+                window.set_icon(icon);
+            }
 
-        let canvas = window
-            .into_canvas()
-            .build()
-            .context("Failed to build SDL2 canvas")?;
+            let mut canvas_builder = window.into_canvas();
+            if self.vsync {
+                canvas_builder = canvas_builder.present_vsync();
+            }
+
+            let canvas = canvas_builder
+                .build()
+                .context("Failed to build SDL2 canvas")?;
+
+            Canvas::Window(canvas)
+        };
 
         let mut ctx = Context {
             sdl_context,
             canvas,
             is_running: false,
-            tick_rate: time::f64_to_duration(self.tick_rate),
+            quit_on_escape: self.quit_on_escape,
+            time,
+            timers: Timers::new(),
             fps_tracker: FpsTracker::new(),
             world: World::new(),
+            worlds: Worlds::new(),
+            schedule: Schedule::new(),
             graphics: GraphicsContext::new(),
             keyboard: KeyboardContext::new(),
             mouse: MouseContext::new(),
+            states: StateStack::new(),
             game: (),
         };
 
         let game_ctx = build_game_ctx(&mut ctx)?;
+        let schedule = ctx.schedule.into_game()?;
+        let timers = ctx.timers.into_game()?;
 
         Ok(Context {
             sdl_context: ctx.sdl_context,
             canvas: ctx.canvas,
             is_running: ctx.is_running,
-            tick_rate: ctx.tick_rate,
+            quit_on_escape: ctx.quit_on_escape,
+            time: ctx.time,
+            timers,
             fps_tracker: ctx.fps_tracker,
             world: ctx.world,
+            worlds: ctx.worlds,
+            schedule,
             graphics: ctx.graphics,
             keyboard: ctx.keyboard,
             mouse: ctx.mouse,
+            states: StateStack::new(),
             game: game_ctx,
         })
     }
@@ -243,8 +603,19 @@ impl<'a> Default for ContextBuilder<'a> {
             height: 600,
             vsync: true,
             tick_rate: 1.0 / 60.0,
-            fullscreen: false,
+            target_frame_rate: None,
+            max_updates_per_frame: time::DEFAULT_MAX_UPDATES_PER_FRAME,
+            timestep: Timestep::Fixed,
+            fullscreen: Fullscreen::Off,
+            resizable: false,
+            borderless: false,
+            high_dpi: false,
+            min_size: None,
+            max_size: None,
+            icon: None,
+            position: None,
             quit_on_escape: true,
+            headless: false,
         }
     }
 }
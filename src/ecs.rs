@@ -1,27 +1,196 @@
 mod allocator;
+mod bundle;
+mod commands;
 mod entity;
 mod entity_builder;
 mod generation;
+mod hierarchy;
+mod hooks;
+mod inspect;
+mod name;
+mod prefab;
+mod query;
+mod resources;
+mod scene;
+mod schedule;
+mod snapshot;
+mod spatial;
+mod storage;
+mod system_context;
+#[cfg(test)]
+mod test_util;
+mod transform;
 mod world;
+mod worlds;
 
 pub(crate) use self::allocator::*;
+pub use self::bundle::*;
+pub use self::commands::*;
 pub use self::entity::*;
 pub use self::entity_builder::*;
 pub(crate) use self::generation::*;
-pub(crate) use self::world::*;
+pub use self::hierarchy::*;
+pub(crate) use self::hooks::*;
+pub use self::inspect::*;
+pub use self::name::*;
+pub use self::prefab::*;
+pub use self::query::*;
+pub use self::resources::*;
+pub use self::scene::*;
+pub use self::schedule::*;
+pub use self::snapshot::*;
+pub use self::spatial::*;
+pub use self::storage::*;
+pub use self::system_context::*;
+pub use self::transform::*;
+pub use self::world::*;
+pub use self::worlds::*;
 
-use crate::Context;
+use std::hash::Hash;
+use std::time::Duration;
 
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::error::{AnyhowContext, EcsError, PrefabError};
+use crate::graphics::Rectangle;
+use crate::{Context, Result, Vector2f};
+
+/// A piece of data that can be attached to an entity.
+///
+/// Zero-sized components work well as tags, since they take up no space for
+/// their data and can be matched with the [`With`] and [`Without`] filters:
+///
+/// ```ignore
+/// struct Enemy;
+///
+/// impl Component for Enemy {}
+///
+/// for (entity, transform) in ecs::query_filtered::<_, &Transform, With<Enemy>>(ctx)? {
+///     // ...
+/// }
+/// ```
 pub trait Component: Send + Sync + 'static {}
 
 pub fn create_entity<G>(ctx: &mut Context<G>) -> EntityBuilder {
     EntityBuilder::new(&mut ctx.world)
 }
 
+/// Spawns an entity for each of the given bundles of components, returning
+/// the new entities in the same order.
+///
+/// ```ignore
+/// let orcs = ecs::spawn_batch(ctx, (0..1000).map(|_| (Transform::default(), Velocity::random())));
+/// ```
+pub fn spawn_batch<G, B, I>(ctx: &mut Context<G>, bundles: I) -> Vec<Entity>
+where
+    B: Bundle,
+    I: IntoIterator<Item = B>,
+{
+    ctx.world.spawn_batch(bundles)
+}
+
 pub fn entities<G>(ctx: &Context<G>) -> Vec<Entity> {
     ctx.world.entities.clone()
 }
 
+/// Returns an iterator over all of the entities that have the components in
+/// the given [`Query`], along with those components.
+///
+/// ```ignore
+/// for (entity, (transform, velocity)) in ecs::query::<_, (&mut Transform, &Velocity)>(ctx)? {
+///     transform.position += velocity.0;
+/// }
+/// ```
+///
+/// Returns an error if the query accesses the same component mutably more than once.
+pub fn query<G, Q: Query>(ctx: &mut Context<G>) -> Result<QueryIter<'_, Q>> {
+    Ok(ctx.world.query()?)
+}
+
+/// Returns an iterator over all of the entities that have the components in
+/// the given [`Query`] and match the given [`QueryFilter`].
+pub fn query_filtered<G, Q: Query, F: QueryFilter>(
+    ctx: &mut Context<G>,
+) -> Result<QueryIter<'_, Q, F>> {
+    Ok(ctx.world.query_filtered()?)
+}
+
+/// Inserts a [`Resource`] into the world, returning the previous resource of
+/// the same type, if there was one.
+pub fn insert_resource<G, T: Resource>(ctx: &mut Context<G>, resource: T) -> Option<T> {
+    ctx.world.insert_resource(resource)
+}
+
+/// Returns whether the world contains a [`Resource`] of type `T`.
+pub fn has_resource<G, T: Resource>(ctx: &Context<G>) -> bool {
+    ctx.world.has_resource::<T>()
+}
+
+/// Returns a reference to the [`Resource`] of type `T`.
+///
+/// Returns an error if the world does not contain a resource of type `T`.
+pub fn resource<G, T: Resource>(ctx: &Context<G>) -> Result<&T> {
+    Ok(ctx.world.resource()?)
+}
+
+/// Returns a mutable reference to the [`Resource`] of type `T`.
+///
+/// Returns an error if the world does not contain a resource of type `T`.
+pub fn resource_mut<G, T: Resource>(ctx: &mut Context<G>) -> Result<&mut T> {
+    Ok(ctx.world.resource_mut()?)
+}
+
+/// Removes the [`Resource`] of type `T` from the world and returns it, if there was one.
+pub fn remove_resource<G, T: Resource>(ctx: &mut Context<G>) -> Option<T> {
+    ctx.world.remove_resource()
+}
+
+/// Temporarily removes the [`Resource`] of type `T` from the world so that it
+/// can be used alongside the rest of the context, such as in a query.
+///
+/// Returns an error if the world does not contain a resource of type `T`.
+pub fn resource_scope<G, T, F, U>(ctx: &mut Context<G>, f: F) -> Result<U>
+where
+    T: Resource,
+    F: FnOnce(&mut Context<G>, &mut T) -> U,
+{
+    let mut resource = ctx
+        .world
+        .remove_resource::<T>()
+        .ok_or_else(|| EcsError::MissingResource(std::any::type_name::<T>()))?;
+
+    let result = f(ctx, &mut resource);
+
+    ctx.world.insert_resource(resource);
+
+    Ok(result)
+}
+
+/// Adds a [`System`] to be run by the engine in the given [`Stage`].
+pub fn add_system<G>(ctx: &mut Context<G>, stage: Stage, system: System<G>) {
+    ctx.schedule.add_system(stage, system);
+}
+
+/// Sets whether the systems in each stage run one after another on the main
+/// thread, or in parallel when their declared accesses don't conflict.
+pub fn set_execution_mode<G>(ctx: &mut Context<G>, mode: ExecutionMode) {
+    ctx.schedule.set_mode(mode);
+}
+
+/// Returns the systems that cannot run in parallel with an earlier system in
+/// the same stage because their accesses conflict, for debugging the schedule.
+///
+/// Returns an error if the ordering constraints between the systems are invalid.
+pub fn system_conflicts<G>(ctx: &mut Context<G>) -> Result<Vec<SystemConflict>> {
+    Ok(ctx.schedule.conflicts()?)
+}
+
+/// Returns whether the given entity is alive.
+pub fn is_alive<G>(ctx: &Context<G>, entity: Entity) -> bool {
+    ctx.world.is_alive(entity)
+}
+
 pub fn has_component<G, T: Component>(ctx: &mut Context<G>, entity: Entity) -> bool {
     ctx.world.has_component::<T>(entity)
 }
@@ -34,6 +203,366 @@ pub fn get_component_mut<G, T: Component>(ctx: &mut Context<G>, entity: Entity)
     ctx.world.get_component_mut(entity)
 }
 
-pub fn delete_entity<G>(ctx: &mut Context<G>, entity: Entity) {
-    unimplemented!()
+/// Returns an iterator over the entities that have had their component of type
+/// `T` removed during the current tick, including by being deleted.
+///
+/// The removed components are cleared at the end of each fixed update.
+pub fn removed<G, T: Component>(ctx: &Context<G>) -> impl Iterator<Item = Entity> + '_ {
+    ctx.world.removed::<T>()
+}
+
+/// Inserts a component on the given entity, returning the previous component
+/// of the same type, if there was one.
+///
+/// Returns an error if the entity is not alive.
+pub fn insert_component<G, T: Component>(
+    ctx: &mut Context<G>,
+    entity: Entity,
+    component: T,
+) -> Result<Option<T>> {
+    Ok(ctx.world.insert_component(entity, component)?)
+}
+
+/// Removes the component of type `T` from the given entity and returns it.
+///
+/// Returns `None` if the entity is not alive or does not have a component of type `T`.
+pub fn remove_component<G, T: Component>(ctx: &mut Context<G>, entity: Entity) -> Option<T> {
+    ctx.world.remove_component(entity)
+}
+
+/// Removes the component of type `T` from the given entity and returns it.
+///
+/// Returns an error if the entity is not alive or does not have a component of type `T`.
+pub fn take_component<G, T: Component>(ctx: &mut Context<G>, entity: Entity) -> Result<T> {
+    Ok(ctx.world.take_component(entity)?)
+}
+
+/// Returns an entity with the given [`Name`], if there is one.
+///
+/// If more than one entity has the name, the one that was named first is returned.
+pub fn find_by_name<G>(ctx: &Context<G>, name: &str) -> Option<Entity> {
+    ctx.world.find_by_name(name)
+}
+
+/// Returns all of the entities with the given [`Name`].
+pub fn find_all_by_name<'a, G>(ctx: &'a Context<G>, name: &str) -> &'a [Entity] {
+    ctx.world.find_all_by_name(name)
+}
+
+/// Returns a [`WorldReport`] describing the entities, components and storages
+/// in the world, for debugging.
+///
+/// ```ignore
+/// println!("{}", ecs::dump_world(ctx));
+/// ```
+///
+/// Use [`World::dump`] to inspect a world without a [`Context`].
+pub fn dump_world<G>(ctx: &Context<G>) -> WorldReport {
+    ctx.world.dump()
+}
+
+/// Includes the values of components of type `T` in the reports returned by
+/// [`dump_world`], using their [`Debug`](std::fmt::Debug) representation.
+pub fn register_debug<G, T: Component + std::fmt::Debug>(ctx: &mut Context<G>) {
+    ctx.world.register_debug::<T>()
+}
+
+/// Deletes the given entity, along with all of its components.
+///
+/// Returns an error if the entity has already been deleted.
+pub fn delete_entity<G>(ctx: &mut Context<G>, entity: Entity) -> Result<()> {
+    ctx.world.kill_entity(entity)?;
+    Ok(())
+}
+
+/// Deletes all of the given entities, along with their components.
+///
+/// Returns an error for the first entity that has already been deleted. Any
+/// entities before it will have been deleted.
+pub fn despawn_batch<G, I>(ctx: &mut Context<G>, entities: I) -> Result<()>
+where
+    I: IntoIterator<Item = Entity>,
+{
+    ctx.world.kill_entities(entities)?;
+    Ok(())
+}
+
+/// Deletes the given entity, along with all of its descendants.
+///
+/// Returns an error if the entity has already been deleted.
+pub fn delete_entity_recursive<G>(ctx: &mut Context<G>, entity: Entity) -> Result<()> {
+    ctx.world.kill_entity_recursive(entity)?;
+    Ok(())
+}
+
+/// Returns the parent of the given entity, if it has one.
+pub fn parent<G>(ctx: &Context<G>, entity: Entity) -> Option<Entity> {
+    ctx.world.parent(entity)
+}
+
+/// Returns the children of the given entity.
+pub fn children<G>(ctx: &Context<G>, entity: Entity) -> &[Entity] {
+    ctx.world.children(entity)
+}
+
+/// Makes `parent` the parent of `child`, removing `child` from its previous
+/// parent, if it had one.
+///
+/// Returns an error if either entity is not alive, or if `parent` is `child`
+/// or one of its descendants.
+pub fn set_parent<G>(ctx: &mut Context<G>, child: Entity, parent: Entity) -> Result<()> {
+    ctx.world.set_parent(child, parent)?;
+    Ok(())
+}
+
+/// Removes the parent of the given entity, returning the previous parent if
+/// there was one.
+///
+/// Returns an error if the entity is not alive.
+pub fn remove_parent<G>(ctx: &mut Context<G>, child: Entity) -> Result<Option<Entity>> {
+    Ok(ctx.world.remove_parent(child)?)
+}
+
+/// Registers a component type so that it is included in saved scenes and can
+/// be loaded from them under the given name.
+pub fn register_component<G, T>(ctx: &mut Context<G>, name: &'static str)
+where
+    T: Component + Serialize + DeserializeOwned,
+{
+    ctx.world.register_component::<T>(name);
+}
+
+/// Registers a component type that refers to other entities, so that those
+/// references are remapped when it is loaded from a scene.
+pub fn register_component_with_entities<G, T>(ctx: &mut Context<G>, name: &'static str)
+where
+    T: Component + Serialize + DeserializeOwned + MapEntities,
+{
+    ctx.world.register_component_with_entities::<T>(name);
+}
+
+/// Saves all of the entities in the world, along with their registered
+/// components, to a JSON scene file.
+pub fn save_scene<G>(ctx: &Context<G>, filename: &str) -> Result<()> {
+    let json = ctx.world.save_scene()?.to_json()?;
+
+    std::fs::write(filename, json)
+        .with_context(|| format!("Failed to save scene to file: {}", filename))?;
+
+    Ok(())
+}
+
+/// Loads the entities from a JSON scene file into the world, returning the
+/// mapping from the entities in the file to the newly created entities.
+///
+/// Returns an error if the scene contains a component that has not been
+/// registered.
+pub fn load_scene<G>(ctx: &mut Context<G>, filename: &str) -> Result<EntityMap> {
+    let json = std::fs::read_to_string(filename)
+        .with_context(|| format!("Failed to load scene from file: {}", filename))?;
+
+    let scene = Scene::from_json(&json)
+        .with_context(|| format!("Failed to load scene from file: {}", filename))?;
+
+    Ok(ctx.world.load_scene(&scene)?)
+}
+
+/// Returns a [`PrefabBuilder`] for defining a prefab with the given name.
+pub fn create_prefab<'a, G>(ctx: &'a mut Context<G>, name: &str) -> PrefabBuilder<'a> {
+    ctx.world.create_prefab(name)
+}
+
+/// Loads all of the prefabs from a JSON prefab file.
+///
+/// The file is watched for changes, and its prefabs are reloaded automatically
+/// every [poll interval](set_prefab_poll_interval).
+pub fn load_prefabs<G>(ctx: &mut Context<G>, filename: &str) -> Result<()> {
+    ctx.world.load_prefabs(filename)?;
+    Ok(())
+}
+
+/// Returns a [`PrefabInstanceBuilder`] for spawning a new entity from the
+/// prefab with the given name.
+///
+/// ```ignore
+/// let orc = ecs::spawn_prefab(ctx, "orc_grunt")
+///     .with(Transform::from_position(spawn_point))
+///     .build()?;
+/// ```
+pub fn spawn_prefab<'a, G>(ctx: &'a mut Context<G>, name: &str) -> PrefabInstanceBuilder<'a> {
+    ctx.world.spawn_prefab(name)
+}
+
+/// Sets how often the prefab files are checked for changes and reloaded, or
+/// stops checking them if the interval is `None`.
+pub fn set_prefab_poll_interval<G>(ctx: &mut Context<G>, poll_interval: Option<Duration>) {
+    ctx.world.set_prefab_poll_interval(poll_interval);
+}
+
+/// Returns the error from the most recent automatic reload of the prefab files,
+/// if it failed.
+pub fn prefab_reload_error<G>(ctx: &Context<G>) -> Option<&PrefabError> {
+    ctx.world.prefab_reload_error()
+}
+
+/// Registers a hook that is run whenever a component of type `T` is added to an entity.
+///
+/// ```ignore
+/// ecs::on_add::<_, Body>(ctx, |entity, body, _commands| {
+///     println!("{:?} entered the broadphase at {:?}", entity, body.position);
+/// });
+/// ```
+pub fn on_add<G, T: Component>(
+    ctx: &mut Context<G>,
+    hook: impl FnMut(Entity, &T, &mut Commands) + Send + Sync + 'static,
+) {
+    ctx.world.on_add(hook)
+}
+
+/// Registers a hook that is run whenever a component of type `T` is removed
+/// from an entity, including when the entity is deleted.
+pub fn on_remove<G, T: Component>(
+    ctx: &mut Context<G>,
+    hook: impl FnMut(Entity, &T, &mut Commands) + Send + Sync + 'static,
+) {
+    ctx.world.on_remove(hook)
+}
+
+/// Registers a hook that is run whenever an entity is deleted.
+pub fn on_despawn<G>(
+    ctx: &mut Context<G>,
+    hook: impl FnMut(Entity, &mut Commands) + Send + Sync + 'static,
+) {
+    ctx.world.on_despawn(hook)
+}
+
+/// Includes components of type `T` in [`Snapshot`]s of the world.
+pub fn register_snapshot<G, T: Component + Clone + PartialEq + Hash>(ctx: &mut Context<G>) {
+    ctx.world.register_snapshot::<T>()
+}
+
+/// Returns a [`Snapshot`] of the entities in the world and their registered components.
+///
+/// ```ignore
+/// let snapshot = ecs::snapshot(ctx);
+/// // ...
+/// ecs::restore(ctx, &snapshot);
+/// ```
+pub fn snapshot<G>(ctx: &Context<G>) -> Snapshot {
+    ctx.world.snapshot()
+}
+
+/// Restores the world to the state in the given [`Snapshot`].
+pub fn restore<G>(ctx: &mut Context<G>, snapshot: &Snapshot) {
+    ctx.world.restore(snapshot)
+}
+
+/// Creates a new, empty world with the given name, which can be built up
+/// while another world is active.
+///
+/// The world that is active when the game starts is named [`MAIN_WORLD`].
+///
+/// The new world starts with the active world's registered components (for
+/// scenes, snapshots and debugging), hooks, prefabs and spatial cell size.
+/// Anything registered afterwards only applies to the world it is registered on.
+///
+/// ```ignore
+/// let level = ecs::create_world(ctx, "level_2")?;
+/// level.create_entity().with(Transform::default()).build();
+/// // ...
+/// ecs::set_active_world(ctx, "level_2")?;
+/// ```
+///
+/// Returns an error if a world with the same name already exists.
+pub fn create_world<'a, G>(ctx: &'a mut Context<G>, name: &str) -> Result<&'a mut World> {
+    Ok(ctx.worlds.create(name, &ctx.world)?)
+}
+
+/// Returns the world with the given name, if it exists.
+pub fn world_mut<'a, G>(ctx: &'a mut Context<G>, name: &str) -> Option<&'a mut World> {
+    ctx.worlds.get_mut(name, &mut ctx.world)
+}
+
+/// Returns the name of the active world.
+pub fn active_world<G>(ctx: &Context<G>) -> &str {
+    ctx.worlds.active()
+}
+
+/// Makes the world with the given name the active world.
+///
+/// The systems and the rest of the `ecs` functions operate on the active
+/// world. The previously active world is kept until it is dropped.
+///
+/// Each world has its own registrations, so a world should be created with
+/// [`create_world`] after everything it needs has been registered.
+///
+/// Returns an error if no world has the given name.
+pub fn set_active_world<G>(ctx: &mut Context<G>, name: &str) -> Result<()> {
+    ctx.worlds.activate(name, &mut ctx.world)?;
+    Ok(())
+}
+
+/// Removes the world with the given name and returns it.
+///
+/// Returns an error if the world is active, or if no world has the given name.
+pub fn drop_world<G>(ctx: &mut Context<G>, name: &str) -> Result<World> {
+    Ok(ctx.worlds.remove(name)?)
+}
+
+/// Moves the given entities, along with all of their components, from one
+/// world to another, returning the mapping from the moved entities to the
+/// entities that replace them.
+///
+/// See [`World::move_entities`] for how references to the moved entities are updated.
+pub fn move_entities<G>(
+    ctx: &mut Context<G>,
+    from: &str,
+    to: &str,
+    entities: &[Entity],
+) -> Result<EntityMap> {
+    Ok(ctx
+        .worlds
+        .move_entities(from, to, entities, &mut ctx.world)?)
+}
+
+/// Returns the entities whose [`Bounds`] overlap the given rectangle.
+///
+/// The spatial index is updated once per update, so entities that moved since
+/// then are found at their previous position. Entities that were deleted are
+/// not found.
+pub fn entities_in_rect<G>(ctx: &Context<G>, rect: &Rectangle<f32>) -> Vec<Entity> {
+    ctx.world.entities_in_rect(rect)
+}
+
+/// Returns the entities whose [`Bounds`] are within the given distance of a point.
+///
+/// ```ignore
+/// for entity in ecs::entities_in_radius(ctx, explosion.position, 100.0) {
+///     // ...
+/// }
+/// ```
+pub fn entities_in_radius<G>(ctx: &Context<G>, center: Vector2f, radius: f32) -> Vec<Entity> {
+    ctx.world.entities_in_radius(center, radius)
+}
+
+/// Returns up to `count` entities whose [`Bounds`] are closest to the given
+/// point, nearest first.
+pub fn nearest_entities<G>(ctx: &Context<G>, point: Vector2f, count: usize) -> Vec<Entity> {
+    ctx.world.nearest_entities(point, count)
+}
+
+/// Sets the size of the cells in the spatial index, which should be around
+/// the size of a typical entity.
+///
+/// Returns an error if the size is not a positive, finite number.
+pub fn set_spatial_cell_size<G>(ctx: &mut Context<G>, cell_size: f32) -> Result<()> {
+    ctx.world.set_spatial_cell_size(cell_size)?;
+    Ok(())
+}
+
+/// Returns the [`Commands`] buffer for recording deferred changes to the world.
+///
+/// The recorded commands are applied after each call to [`State::update`](crate::State::update).
+pub fn commands<G>(ctx: &mut Context<G>) -> &mut Commands {
+    &mut ctx.world.commands
 }
@@ -1,7 +1,9 @@
 use std::collections::VecDeque;
 
 use crate::ecs::{Entity, Generation, ZeroableGeneration};
+use crate::error::EcsError;
 
+#[derive(Clone, PartialEq, Hash)]
 pub(crate) struct Allocator {
     generations: Vec<Option<Generation>>,
     cache: VecDeque<i32>,
@@ -30,18 +32,40 @@ impl Allocator {
         Entity::new(generation, id)
     }
 
+    /// Reserves space for at least the given number of additional entities.
+    pub fn reserve(&mut self, additional: usize) {
+        let reused = additional.min(self.cache.len());
+        self.generations.reserve(additional - reused);
+    }
+
+    /// Returns the number of IDs that are waiting to be reused.
+    pub fn free_len(&self) -> usize {
+        self.cache.len()
+    }
+
     pub fn is_alive(&self, entity: Entity) -> bool {
-        entity.generation() == self.get_generation(entity)
+        self.generations
+            .get(entity.id() as usize)
+            .copied()
+            .flatten()
+            .is_some_and(|generation| generation.is_alive() && generation == entity.generation())
     }
 
-    pub fn kill(&mut self, entity: Entity) {
-        self.kill_many(std::iter::once(entity));
+    pub fn kill(&mut self, entity: Entity) -> Result<(), EcsError> {
+        self.kill_many(std::iter::once(entity))
     }
 
-    pub fn kill_many<I: IntoIterator<Item = Entity>>(&mut self, entities: I) {
+    /// Kills all of the given entities.
+    ///
+    /// Stops at the first entity that is not alive and returns an error for it.
+    /// Any entities before it will have already been killed.
+    pub fn kill_many<I: IntoIterator<Item = Entity>>(
+        &mut self,
+        entities: I,
+    ) -> Result<(), EcsError> {
         for entity in entities {
             if !self.is_alive(entity) {
-                panic!("Wrong generation")
+                return Err(EcsError::DeadEntity(entity));
             }
 
             let id = entity.id();
@@ -52,15 +76,8 @@ impl Allocator {
 
             self.cache.push_back(id);
         }
-    }
-
-    fn get_generation(&self, entity: Entity) -> Generation {
-        if self.generations.len() <= entity.id() as usize {
-            return Generation::one();
-        }
 
-        let generation = self.generations[entity.id() as usize];
-        generation.unwrap_or_else(Generation::one)
+        Ok(())
     }
 
     fn update_generation_length(&mut self, desired_length: usize) {
@@ -0,0 +1,40 @@
+use crate::ecs::{Component, Entity, World};
+
+/// A group of components that are added to an entity together.
+///
+/// This is implemented for tuples of up to eight components, and is used to
+/// spawn many entities at once without boxing their components.
+pub trait Bundle: Send + Sync + 'static {
+    /// Reserves space in the storage of each component for the given number
+    /// of additional entities.
+    fn reserve(world: &mut World, additional: usize);
+
+    /// Adds the components to the given entity.
+    fn attach_to(self, world: &mut World, entity: Entity);
+}
+
+macro_rules! impl_bundle_for_tuple {
+    ( $( $name:ident ),* ) => {
+        #[allow(non_snake_case, unused_variables)]
+        impl<$( $name: Component ),*> Bundle for ( $( $name, )* ) {
+            fn reserve(world: &mut World, additional: usize) {
+                $( world.reserve_components::<$name>(additional); )*
+            }
+
+            fn attach_to(self, world: &mut World, entity: Entity) {
+                let ( $( $name, )* ) = self;
+                $( world.attach_component(entity, $name); )*
+            }
+        }
+    };
+}
+
+impl_bundle_for_tuple!();
+impl_bundle_for_tuple!(A);
+impl_bundle_for_tuple!(A, B);
+impl_bundle_for_tuple!(A, B, C);
+impl_bundle_for_tuple!(A, B, C, D);
+impl_bundle_for_tuple!(A, B, C, D, E);
+impl_bundle_for_tuple!(A, B, C, D, E, F);
+impl_bundle_for_tuple!(A, B, C, D, E, F, G);
+impl_bundle_for_tuple!(A, B, C, D, E, F, G, H);
@@ -0,0 +1,142 @@
+use std::any::TypeId;
+
+use crate::ecs::{push_component, BoxedComponent, Component, Entity, World};
+use crate::error::EcsError;
+
+enum Command {
+    Spawn(Vec<BoxedComponent>),
+    Despawn(Entity),
+    Insert(Entity, BoxedComponent),
+    Remove(Entity, TypeId),
+    DespawnRecursive(Entity),
+    SetParent(Entity, Entity),
+    RemoveParent(Entity),
+}
+
+/// A buffer of changes to the [`World`] that are applied at a later point.
+///
+/// Commands can be recorded while iterating over entities and are applied
+/// after [`State::update`](crate::State::update) has run.
+#[derive(Default)]
+pub struct Commands {
+    queue: Vec<Command>,
+}
+
+impl Commands {
+    /// Returns a new, empty [`Commands`] buffer.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of commands waiting to be applied.
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    /// Returns whether there are no commands waiting to be applied.
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    /// Records the creation of a new entity.
+    pub fn spawn(&mut self) -> SpawnCommandBuilder<'_> {
+        SpawnCommandBuilder {
+            commands: self,
+            components: Vec::new(),
+        }
+    }
+
+    /// Records the deletion of the given entity.
+    pub fn despawn(&mut self, entity: Entity) {
+        self.queue.push(Command::Despawn(entity));
+    }
+
+    /// Records the deletion of the given entity, along with all of its descendants.
+    pub fn despawn_recursive(&mut self, entity: Entity) {
+        self.queue.push(Command::DespawnRecursive(entity));
+    }
+
+    /// Records making `parent` the parent of `child`.
+    pub fn set_parent(&mut self, child: Entity, parent: Entity) {
+        self.queue.push(Command::SetParent(child, parent));
+    }
+
+    /// Records the removal of the parent of the given entity.
+    pub fn remove_parent(&mut self, child: Entity) {
+        self.queue.push(Command::RemoveParent(child));
+    }
+
+    /// Records the insertion of a component on the given entity.
+    ///
+    /// If the entity already has a component of this type it will be replaced.
+    pub fn insert<T: Component>(&mut self, entity: Entity, component: T) {
+        self.queue
+            .push(Command::Insert(entity, Box::new(component)));
+    }
+
+    /// Records the removal of a component from the given entity.
+    pub fn remove<T: Component>(&mut self, entity: Entity) {
+        self.queue.push(Command::Remove(entity, TypeId::of::<T>()));
+    }
+
+    /// Moves all of the commands from `other` to the end of this buffer.
+    pub(crate) fn append(&mut self, other: &mut Commands) {
+        self.queue.append(&mut other.queue);
+    }
+
+    /// Applies all of the recorded commands to the [`World`], in the order in
+    /// which they were recorded.
+    ///
+    /// Stops at the first command that fails. The remaining commands are
+    /// discarded.
+    pub(crate) fn apply(&mut self, world: &mut World) -> Result<(), EcsError> {
+        for command in self.queue.drain(..) {
+            match command {
+                Command::Spawn(components) => {
+                    world.spawn(components);
+                }
+                Command::Despawn(entity) => world.kill_entity(entity)?,
+                Command::Insert(entity, component) => {
+                    if !world.allocator.is_alive(entity) {
+                        return Err(EcsError::DeadEntity(entity));
+                    }
+
+                    component.attach_to(world, entity);
+                }
+                Command::Remove(entity, component_type) => {
+                    if !world.allocator.is_alive(entity) {
+                        return Err(EcsError::DeadEntity(entity));
+                    }
+
+                    world.remove_component_by_type(entity, component_type);
+                }
+                Command::DespawnRecursive(entity) => world.kill_entity_recursive(entity)?,
+                Command::SetParent(child, parent) => world.set_parent(child, parent)?,
+                Command::RemoveParent(child) => {
+                    world.remove_parent(child)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// A builder for recording the creation of a new entity in [`Commands`].
+pub struct SpawnCommandBuilder<'a> {
+    commands: &'a mut Commands,
+    components: Vec<BoxedComponent>,
+}
+
+impl<'a> SpawnCommandBuilder<'a> {
+    /// Adds a component to the entity.
+    pub fn with<T: Component>(mut self, component: T) -> Self {
+        push_component(&mut self.components, Box::new(component));
+        self
+    }
+
+    /// Records the creation of the entity.
+    pub fn build(self) {
+        self.commands.queue.push(Command::Spawn(self.components));
+    }
+}
@@ -1,3 +1,6 @@
+use serde::de::{self, Deserialize, Deserializer};
+use serde::{Serialize, Serializer};
+
 use crate::ecs::Generation;
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
@@ -15,4 +18,63 @@ impl Entity {
     pub(crate) fn id(&self) -> i32 {
         self.1
     }
+
+    /// Returns the entity encoded as a single integer, suitable for storing in
+    /// save files or sending over the network.
+    ///
+    /// The entity can be recreated using [`Entity::from_bits`].
+    pub fn to_bits(self) -> u64 {
+        (u64::from(self.generation().id() as u32) << 32) | u64::from(self.id() as u32)
+    }
+
+    /// Recreates an entity from an integer produced by [`Entity::to_bits`].
+    ///
+    /// Returns `None` if the integer does not represent a valid entity.
+    pub fn from_bits(bits: u64) -> Option<Self> {
+        let generation = Generation::new((bits >> 32) as u32 as i32).ok()?;
+        let id = bits as u32 as i32;
+
+        if !generation.is_alive() || id < 0 {
+            return None;
+        }
+
+        Some(Self::new(generation, id))
+    }
+}
+
+/// Entities are serialized using [`Entity::to_bits`].
+impl Serialize for Entity {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(self.to_bits())
+    }
+}
+
+impl<'de> Deserialize<'de> for Entity {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let bits = u64::deserialize(deserializer)?;
+
+        Entity::from_bits(bits)
+            .ok_or_else(|| de::Error::custom(format!("{} is not a valid entity", bits)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bits_round_trip() {
+        let entity = Entity::new(Generation::new(3).unwrap(), 42);
+
+        assert_eq!(Entity::from_bits(entity.to_bits()), Some(entity));
+    }
+
+    #[test]
+    fn from_bits_rejects_invalid_generations() {
+        assert_eq!(Entity::from_bits(42), None);
+        assert_eq!(
+            Entity::from_bits((u64::from(-3i32 as u32) << 32) | 42),
+            None
+        );
+    }
 }
@@ -0,0 +1,266 @@
+use crate::ecs::{Component, Entity, EntityMap, MapEntities, World};
+use crate::error::{EcsError, SceneError};
+
+/// The parent of an entity.
+///
+/// Maintained by the [`World`] through [`World::set_parent`] and [`World::remove_parent`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Parent(Entity);
+
+impl Parent {
+    /// Returns the parent entity.
+    pub fn get(&self) -> Entity {
+        self.0
+    }
+}
+
+impl Component for Parent {}
+
+impl MapEntities for Parent {
+    fn map_entities(&mut self, entities: &EntityMap) -> Result<(), SceneError> {
+        self.0 = entities.get(self.0)?;
+        Ok(())
+    }
+}
+
+/// The children of an entity.
+///
+/// Maintained by the [`World`] through [`World::set_parent`] and [`World::remove_parent`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct Children(Vec<Entity>);
+
+impl Children {
+    /// Returns the child entities, in the order in which they were added.
+    pub fn as_slice(&self) -> &[Entity] {
+        &self.0
+    }
+
+    /// Returns an iterator over the child entities.
+    pub fn iter(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.0.iter().copied()
+    }
+}
+
+impl Component for Children {}
+
+impl MapEntities for Children {
+    fn map_entities(&mut self, entities: &EntityMap) -> Result<(), SceneError> {
+        for child in self.0.iter_mut() {
+            *child = entities.get(*child)?;
+        }
+        Ok(())
+    }
+}
+
+impl World {
+    /// Returns the parent of the given entity, if it has one.
+    pub fn parent(&self, entity: Entity) -> Option<Entity> {
+        self.get_component::<Parent>(entity).map(Parent::get)
+    }
+
+    /// Returns the children of the given entity.
+    pub fn children(&self, entity: Entity) -> &[Entity] {
+        self.get_component::<Children>(entity)
+            .map_or(&[], Children::as_slice)
+    }
+
+    /// Makes `parent` the parent of `child`, removing `child` from its previous
+    /// parent, if it had one.
+    ///
+    /// Returns an error if either entity is not alive, or if `parent` is
+    /// `child` or one of its descendants.
+    pub fn set_parent(&mut self, child: Entity, parent: Entity) -> Result<(), EcsError> {
+        for entity in [child, parent] {
+            if !self.is_alive(entity) {
+                return Err(EcsError::DeadEntity(entity));
+            }
+        }
+
+        let mut ancestor = Some(parent);
+        while let Some(current) = ancestor {
+            if current == child {
+                return Err(EcsError::InvalidParent { child, parent });
+            }
+
+            ancestor = self.parent(current);
+        }
+
+        self.remove_parent(child)?;
+
+        self.attach_component(child, Parent(parent));
+
+        match self.get_component_mut::<Children>(parent) {
+            Some(children) => children.0.push(child),
+            None => {
+                self.attach_component(parent, Children(vec![child]));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Removes the parent of the given entity, returning the previous parent if
+    /// there was one.
+    ///
+    /// Returns an error if the entity is not alive.
+    pub fn remove_parent(&mut self, child: Entity) -> Result<Option<Entity>, EcsError> {
+        if !self.is_alive(child) {
+            return Err(EcsError::DeadEntity(child));
+        }
+
+        let parent = match self.remove_component::<Parent>(child) {
+            Some(parent) => parent.get(),
+            None => return Ok(None),
+        };
+
+        self.remove_child(parent, child);
+
+        Ok(Some(parent))
+    }
+
+    /// Deletes the given entity, along with all of its descendants.
+    ///
+    /// Returns an error if the entity is not alive.
+    pub fn kill_entity_recursive(&mut self, entity: Entity) -> Result<(), EcsError> {
+        if !self.is_alive(entity) {
+            return Err(EcsError::DeadEntity(entity));
+        }
+
+        let mut descendants = vec![entity];
+        let mut index = 0;
+        while index < descendants.len() {
+            descendants.extend_from_slice(self.children(descendants[index]));
+            index += 1;
+        }
+
+        for entity in descendants {
+            self.kill_entity(entity)?;
+        }
+
+        Ok(())
+    }
+
+    /// Detaches the given entity from its parent and its children, ahead of it
+    /// being deleted.
+    pub(crate) fn detach_from_hierarchy(&mut self, entity: Entity) {
+        if let Some(parent) = self.parent(entity) {
+            self.remove_child(parent, entity);
+        }
+
+        if let Some(children) = self.get_component::<Children>(entity).cloned() {
+            for child in children.iter() {
+                self.remove_component::<Parent>(child);
+            }
+        }
+    }
+
+    fn remove_child(&mut self, parent: Entity, child: Entity) {
+        let has_no_children = match self.get_component_mut::<Children>(parent) {
+            Some(children) => {
+                children.0.retain(|&other| other != child);
+                children.0.is_empty()
+            }
+            None => false,
+        };
+
+        if has_no_children {
+            self.remove_component::<Children>(parent);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set_parent_maintains_both_sides() {
+        let mut world = World::new();
+
+        let first_parent = world.create_entity().build();
+        let second_parent = world.create_entity().build();
+        let child = world.create_entity().build();
+
+        world.set_parent(child, first_parent).unwrap();
+
+        assert_eq!(world.parent(child), Some(first_parent));
+        assert_eq!(world.children(first_parent), &[child]);
+
+        world.set_parent(child, second_parent).unwrap();
+
+        assert_eq!(world.parent(child), Some(second_parent));
+        assert!(!world.has_component::<Children>(first_parent));
+        assert_eq!(world.children(second_parent), &[child]);
+
+        assert_eq!(world.remove_parent(child), Ok(Some(second_parent)));
+        assert_eq!(world.parent(child), None);
+        assert!(world.children(second_parent).is_empty());
+    }
+
+    #[test]
+    fn set_parent_rejects_cycles() {
+        let mut world = World::new();
+
+        let grandparent = world.create_entity().build();
+        let parent = world.create_entity().build();
+        let child = world.create_entity().build();
+
+        world.set_parent(parent, grandparent).unwrap();
+        world.set_parent(child, parent).unwrap();
+
+        assert_eq!(
+            world.set_parent(grandparent, child),
+            Err(EcsError::InvalidParent {
+                child: grandparent,
+                parent: child
+            })
+        );
+        assert_eq!(
+            world.set_parent(child, child),
+            Err(EcsError::InvalidParent {
+                child,
+                parent: child
+            })
+        );
+    }
+
+    #[test]
+    fn kill_entity_detaches_from_the_hierarchy() {
+        let mut world = World::new();
+
+        let parent = world.create_entity().build();
+        let child = world.create_entity().build();
+        let grandchild = world.create_entity().build();
+
+        world.set_parent(child, parent).unwrap();
+        world.set_parent(grandchild, child).unwrap();
+
+        world.kill_entity(child).unwrap();
+
+        assert!(world.children(parent).is_empty());
+        assert_eq!(world.parent(grandchild), None);
+        assert!(world.is_alive(grandchild));
+    }
+
+    #[test]
+    fn kill_entity_recursive_kills_all_descendants() {
+        let mut world = World::new();
+
+        let root = world.create_entity().build();
+        let parent = world.create_entity().build();
+        let first_child = world.create_entity().build();
+        let second_child = world.create_entity().build();
+
+        world.set_parent(parent, root).unwrap();
+        world.set_parent(first_child, parent).unwrap();
+        world.set_parent(second_child, parent).unwrap();
+
+        world.kill_entity_recursive(parent).unwrap();
+
+        assert!(world.is_alive(root));
+        assert!(world.children(root).is_empty());
+        assert!(!world.is_alive(parent));
+        assert!(!world.is_alive(first_child));
+        assert!(!world.is_alive(second_child));
+    }
+}
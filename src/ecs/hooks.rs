@@ -0,0 +1,258 @@
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+use crate::ecs::{Commands, Component, Entity, World};
+
+/// Hooks are shared between the worlds that are created from the same world,
+/// so that they can be cloned along with the rest of its registrations.
+type ComponentHook = Arc<Mutex<dyn FnMut(Entity, &dyn Any, &mut Commands) + Send + Sync>>;
+
+type DespawnHook = Arc<Mutex<dyn FnMut(Entity, &mut Commands) + Send + Sync>>;
+
+/// The callbacks that are run when components are added or removed, and when
+/// entities are despawned.
+#[derive(Clone, Default)]
+pub(crate) struct Hooks {
+    on_add: HashMap<TypeId, Vec<ComponentHook>>,
+    on_remove: HashMap<TypeId, Vec<ComponentHook>>,
+    on_despawn: Vec<DespawnHook>,
+}
+
+impl Hooks {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    pub(crate) fn has_component_hooks(&self, component_type: TypeId) -> bool {
+        self.on_add.contains_key(&component_type) || self.on_remove.contains_key(&component_type)
+    }
+
+    pub(crate) fn run_on_add(
+        &mut self,
+        component_type: TypeId,
+        entity: Entity,
+        component: &dyn Any,
+        commands: &mut Commands,
+    ) {
+        for hook in self.on_add.get(&component_type).into_iter().flatten() {
+            lock(hook)(entity, component, commands);
+        }
+    }
+
+    pub(crate) fn run_on_remove(
+        &mut self,
+        component_type: TypeId,
+        entity: Entity,
+        component: &dyn Any,
+        commands: &mut Commands,
+    ) {
+        for hook in self.on_remove.get(&component_type).into_iter().flatten() {
+            lock(hook)(entity, component, commands);
+        }
+    }
+
+    pub(crate) fn run_on_despawn(&mut self, entity: Entity, commands: &mut Commands) {
+        for hook in self.on_despawn.iter() {
+            lock(hook)(entity, commands);
+        }
+    }
+}
+
+/// Locks the given hook, ignoring poisoning, since a hook that panicked is
+/// still safe to run again.
+fn lock<T: ?Sized>(hook: &Mutex<T>) -> MutexGuard<'_, T> {
+    hook.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+fn component_hook<T: Component>(
+    mut hook: impl FnMut(Entity, &T, &mut Commands) + Send + Sync + 'static,
+) -> ComponentHook {
+    Arc::new(Mutex::new(
+        move |entity, component: &dyn Any, commands: &mut Commands| {
+            hook(entity, component.downcast_ref::<T>().unwrap(), commands)
+        },
+    ))
+}
+
+impl World {
+    /// Registers a hook that is run whenever a component of type `T` is added
+    /// to an entity.
+    ///
+    /// Replacing an existing component runs the removal hooks for the old
+    /// component, followed by the addition hooks for the new one.
+    ///
+    /// Hooks can't access the world directly. Any changes they need to make
+    /// should be recorded in the given [`Commands`], which are applied along
+    /// with the rest of the world's commands.
+    pub fn on_add<T: Component>(
+        &mut self,
+        hook: impl FnMut(Entity, &T, &mut Commands) + Send + Sync + 'static,
+    ) {
+        self.hooks
+            .on_add
+            .entry(TypeId::of::<T>())
+            .or_default()
+            .push(component_hook(hook));
+    }
+
+    /// Registers a hook that is run whenever a component of type `T` is
+    /// removed from an entity, including when the entity is deleted.
+    ///
+    /// The hook receives the component before it is dropped.
+    pub fn on_remove<T: Component>(
+        &mut self,
+        hook: impl FnMut(Entity, &T, &mut Commands) + Send + Sync + 'static,
+    ) {
+        self.hooks
+            .on_remove
+            .entry(TypeId::of::<T>())
+            .or_default()
+            .push(component_hook(hook));
+    }
+
+    /// Registers a hook that is run whenever an entity is deleted.
+    ///
+    /// The hook is run before the entity's components are removed.
+    pub fn on_despawn(&mut self, hook: impl FnMut(Entity, &mut Commands) + Send + Sync + 'static) {
+        self.hooks.on_despawn.push(Arc::new(Mutex::new(hook)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct Texture(u32);
+
+    impl Component for Texture {}
+
+    #[derive(Debug, PartialEq)]
+    struct Released(u32);
+
+    impl Component for Released {}
+
+    type Log = Arc<Mutex<Vec<String>>>;
+
+    fn logged_world() -> (World, Log) {
+        let mut world = World::new();
+        let log = Log::default();
+
+        let added = log.clone();
+        world.on_add::<Texture>(move |entity, texture, _| {
+            added
+                .lock()
+                .unwrap()
+                .push(format!("add {} {}", entity.id(), texture.0));
+        });
+
+        let removed = log.clone();
+        world.on_remove::<Texture>(move |entity, texture, _| {
+            removed
+                .lock()
+                .unwrap()
+                .push(format!("remove {} {}", entity.id(), texture.0));
+        });
+
+        let despawned = log.clone();
+        world.on_despawn(move |entity, _| {
+            despawned
+                .lock()
+                .unwrap()
+                .push(format!("despawn {}", entity.id()));
+        });
+
+        (world, log)
+    }
+
+    fn take(log: &Log) -> Vec<String> {
+        std::mem::take(&mut *log.lock().unwrap())
+    }
+
+    #[test]
+    fn hooks_run_when_components_are_added_and_removed() {
+        let (mut world, log) = logged_world();
+
+        let entity = world.create_entity().with(Texture(1)).build();
+        assert_eq!(take(&log), vec!["add 1 1"]);
+
+        world.insert_component(entity, Texture(2)).unwrap();
+        assert_eq!(take(&log), vec!["remove 1 1", "add 1 2"]);
+
+        assert_eq!(world.remove_component::<Texture>(entity), Some(Texture(2)));
+        assert_eq!(take(&log), vec!["remove 1 2"]);
+
+        world.insert_component(entity, Texture(3)).unwrap();
+        world.commands.remove::<Texture>(entity);
+        world.apply_commands().unwrap();
+        assert_eq!(take(&log), vec!["add 1 3", "remove 1 3"]);
+    }
+
+    #[test]
+    fn hooks_run_in_the_order_that_components_were_given() {
+        let (mut world, log) = logged_world();
+        let released = log.clone();
+        world.on_add::<Released>(move |_, component, _| {
+            released
+                .lock()
+                .unwrap()
+                .push(format!("release {}", component.0));
+        });
+
+        // A repeated component type replaces the earlier one in its place.
+        world
+            .create_entity()
+            .with(Released(1))
+            .with(Texture(2))
+            .with(Released(3))
+            .build();
+        assert_eq!(take(&log), vec!["release 3", "add 1 2"]);
+
+        world
+            .commands
+            .spawn()
+            .with(Texture(4))
+            .with(Released(5))
+            .build();
+        world.apply_commands().unwrap();
+        assert_eq!(take(&log), vec!["add 2 4", "release 5"]);
+    }
+
+    #[test]
+    fn hooks_run_when_entities_are_deleted() {
+        let (mut world, log) = logged_world();
+
+        let entity = world.create_entity().with(Texture(1)).build();
+        let other = world.create_entity().build();
+        take(&log);
+
+        world.kill_entity(entity).unwrap();
+        world.kill_entity(other).unwrap();
+
+        assert_eq!(take(&log), vec!["despawn 1", "remove 1 1", "despawn 2"]);
+    }
+
+    #[test]
+    fn hooks_can_record_commands() {
+        let mut world = World::new();
+        world.on_remove::<Texture>(|entity, texture, commands| {
+            commands.insert(entity, Released(texture.0));
+        });
+        world.on_add::<Released>(|_, released, commands| {
+            commands.spawn().with(Texture(released.0 + 1)).build();
+        });
+
+        let entity = world.create_entity().with(Texture(1)).build();
+        world.remove_component::<Texture>(entity);
+        world.apply_commands().unwrap();
+
+        assert_eq!(world.get_component(entity), Some(&Released(1)));
+        let spawned = world
+            .query::<&Texture>()
+            .unwrap()
+            .map(|(_, texture)| texture.0)
+            .collect::<Vec<_>>();
+        assert_eq!(spawned, vec![2]);
+    }
+}
@@ -0,0 +1,280 @@
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::fmt::{self, Debug};
+
+use serde::Serialize;
+
+use crate::ecs::{Bounds, Children, Component, GlobalTransform, Name, Parent, Transform, World};
+
+type DebugFn = fn(&dyn Any) -> String;
+
+/// The component types whose values are included in a [`WorldReport`].
+#[derive(Clone, Default)]
+pub(crate) struct DebugComponents {
+    formatters: HashMap<TypeId, DebugFn>,
+}
+
+impl DebugComponents {
+    /// Returns the set of debug components, with the built-in components
+    /// registered by default.
+    pub(crate) fn new() -> Self {
+        let mut debug_components = Self::default();
+        debug_components.register::<Name>();
+        debug_components.register::<Parent>();
+        debug_components.register::<Children>();
+        debug_components.register::<Transform>();
+        debug_components.register::<GlobalTransform>();
+        debug_components.register::<Bounds>();
+        debug_components
+    }
+
+    fn register<T: Component + Debug>(&mut self) {
+        self.formatters
+            .insert(TypeId::of::<T>(), format_component::<T>);
+    }
+
+    fn format(&self, component_type: TypeId, component: &dyn Any) -> Option<String> {
+        self.formatters
+            .get(&component_type)
+            .map(|format| format(component))
+    }
+}
+
+fn format_component<T: Component + Debug>(component: &dyn Any) -> String {
+    format!("{:?}", component.downcast_ref::<T>().unwrap())
+}
+
+/// A snapshot of the state of a [`World`], for debugging.
+///
+/// The report can be printed as a text table using its [`Display`](fmt::Display)
+/// implementation, or converted to JSON with [`WorldReport::to_json`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct WorldReport {
+    /// The live entities, ordered by ID.
+    pub entities: Vec<EntityReport>,
+
+    /// The number of components in each storage, ordered by component type name.
+    pub storages: Vec<StorageReport>,
+
+    /// The number of entity IDs that are waiting to be reused.
+    pub free_entities: usize,
+}
+
+/// An entity in a [`WorldReport`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EntityReport {
+    /// The ID of the entity.
+    pub id: i32,
+
+    /// The generation of the entity.
+    pub generation: i32,
+
+    /// The components of the entity, ordered by type name.
+    pub components: Vec<ComponentReport>,
+}
+
+/// A component in a [`WorldReport`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ComponentReport {
+    /// The type name of the component.
+    pub name: &'static str,
+
+    /// The [`Debug`] representation of the component, if its type was
+    /// registered with [`World::register_debug`].
+    pub value: Option<String>,
+}
+
+/// A component storage in a [`WorldReport`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StorageReport {
+    /// The type name of the components in the storage.
+    pub component: &'static str,
+
+    /// The number of components in the storage.
+    pub len: usize,
+}
+
+impl WorldReport {
+    /// Returns the report as pretty-printed JSON.
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string_pretty(self)
+    }
+}
+
+impl fmt::Display for WorldReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} entities ({} free IDs)",
+            self.entities.len(),
+            self.free_entities
+        )?;
+
+        writeln!(f, "{:>6} {:>6}  COMPONENTS", "ID", "GEN")?;
+        for entity in &self.entities {
+            let components = entity
+                .components
+                .iter()
+                .map(|component| match &component.value {
+                    Some(value) => format!("{} = {}", component.name, value),
+                    None => component.name.to_string(),
+                })
+                .collect::<Vec<_>>();
+
+            writeln!(
+                f,
+                "{:>6} {:>6}  {}",
+                entity.id,
+                entity.generation,
+                components.join(", ")
+            )?;
+        }
+
+        let width = self
+            .storages
+            .iter()
+            .map(|storage| storage.component.len())
+            .chain(std::iter::once("COMPONENT".len()))
+            .max()
+            .unwrap_or_default();
+
+        writeln!(f)?;
+        writeln!(f, "{:<width$}  {:>6}", "COMPONENT", "LEN", width = width)?;
+        for storage in &self.storages {
+            writeln!(
+                f,
+                "{:<width$}  {:>6}",
+                storage.component,
+                storage.len,
+                width = width
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+impl World {
+    /// Includes the values of components of type `T` in [`WorldReport`]s,
+    /// using their [`Debug`] representation.
+    pub fn register_debug<T: Component + Debug>(&mut self) {
+        self.debug_components.register::<T>();
+    }
+
+    /// Returns a [`WorldReport`] describing the entities, components and
+    /// storages in the world.
+    pub fn dump(&self) -> WorldReport {
+        let mut entities = self
+            .entities
+            .iter()
+            .map(|&entity| {
+                let mut components = self
+                    .storages()
+                    .filter_map(|(component_type, storage)| {
+                        let component = storage.get_any(entity)?;
+
+                        Some(ComponentReport {
+                            name: storage.component_name(),
+                            value: self.debug_components.format(component_type, component),
+                        })
+                    })
+                    .collect::<Vec<_>>();
+                components.sort_by_key(|component| component.name);
+
+                EntityReport {
+                    id: entity.id(),
+                    generation: entity.generation().id(),
+                    components,
+                }
+            })
+            .collect::<Vec<_>>();
+        entities.sort_by_key(|entity| entity.id);
+
+        let mut storages = self
+            .storages()
+            .map(|(_, storage)| StorageReport {
+                component: storage.component_name(),
+                len: storage.len(),
+            })
+            .collect::<Vec<_>>();
+        storages.sort_by_key(|storage| storage.component);
+
+        WorldReport {
+            entities,
+            storages,
+            free_entities: self.allocator.free_len(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The field is only read through the `Debug` implementation.
+    #[allow(dead_code)]
+    #[derive(Debug)]
+    struct Health(u32);
+
+    impl Component for Health {}
+
+    struct Enemy;
+
+    impl Component for Enemy {}
+
+    fn world() -> World {
+        let mut world = World::new();
+        world.register_debug::<Health>();
+
+        let dead = world.create_entity().build();
+        world
+            .create_entity()
+            .with(Name::new("orc"))
+            .with(Health(10))
+            .with(Enemy)
+            .build();
+        world.kill_entity(dead).unwrap();
+
+        world
+    }
+
+    #[test]
+    fn dump_describes_entities_and_storages() {
+        let report = world().dump();
+
+        assert_eq!(report.free_entities, 1);
+        assert_eq!(report.entities.len(), 1);
+
+        let orc = &report.entities[0];
+        assert_eq!((orc.id, orc.generation), (2, 1));
+
+        let components = orc
+            .components
+            .iter()
+            .map(|component| (component.name, component.value.as_deref()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            components,
+            vec![
+                ("peacock::ecs::inspect::tests::Enemy", None),
+                ("peacock::ecs::inspect::tests::Health", Some("Health(10)")),
+                ("peacock::ecs::name::Name", Some("Name(\"orc\")")),
+            ]
+        );
+
+        assert!(report.storages.iter().all(|storage| storage.len == 1));
+    }
+
+    #[test]
+    fn dump_can_be_formatted_as_text_and_json() {
+        let report = world().dump();
+
+        let text = report.to_string();
+        assert!(text.starts_with("1 entities (1 free IDs)\n"));
+        assert!(text.contains("peacock::ecs::inspect::tests::Health = Health(10)"));
+
+        let json = serde_json::from_str::<serde_json::Value>(&report.to_json().unwrap()).unwrap();
+        assert_eq!(json["free_entities"], 1);
+        assert_eq!(json["entities"][0]["components"][1]["value"], "Health(10)");
+    }
+}
@@ -0,0 +1,148 @@
+use std::any::Any;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::ecs::{Component, Entity, World};
+
+/// A name for an entity, which can be used to look the entity up with
+/// [`World::find_by_name`].
+///
+/// To rename an entity, insert a new [`Name`] on it rather than assigning
+/// through [`World::get_component_mut`], so that the lookup stays up to date.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Name(Cow<'static, str>);
+
+impl Name {
+    /// Returns a new [`Name`].
+    pub fn new(name: impl Into<Cow<'static, str>>) -> Self {
+        Self(name.into())
+    }
+
+    /// Returns the name as a string slice.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for Name {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl Component for Name {}
+
+/// The entities with each [`Name`], kept up to date as names are added and removed.
+#[derive(Default)]
+pub(crate) struct NameIndex {
+    entities: HashMap<Cow<'static, str>, Vec<Entity>>,
+}
+
+impl NameIndex {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records the given component for the entity, if it is a [`Name`].
+    pub(crate) fn insert(&mut self, entity: Entity, component: &dyn Any) {
+        if let Some(name) = component.downcast_ref::<Name>() {
+            self.entities
+                .entry(name.0.clone())
+                .or_default()
+                .push(entity);
+        }
+    }
+
+    /// Forgets the given component for the entity, if it is a [`Name`].
+    pub(crate) fn remove(&mut self, entity: Entity, component: &dyn Any) {
+        let Some(name) = component.downcast_ref::<Name>() else {
+            return;
+        };
+
+        if let Some(entities) = self.entities.get_mut(name.as_str()) {
+            entities.retain(|e| *e != entity);
+            if entities.is_empty() {
+                self.entities.remove(name.as_str());
+            }
+        }
+    }
+
+    fn get(&self, name: &str) -> &[Entity] {
+        self.entities.get(name).map_or(&[], Vec::as_slice)
+    }
+}
+
+impl World {
+    /// Returns an entity with the given [`Name`], if there is one.
+    ///
+    /// If more than one entity has the name, the one that was named first is returned.
+    pub fn find_by_name(&self, name: &str) -> Option<Entity> {
+        self.names.get(name).first().copied()
+    }
+
+    /// Returns all of the entities with the given [`Name`], in the order in
+    /// which they were named.
+    pub fn find_all_by_name(&self, name: &str) -> &[Entity] {
+        self.names.get(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::ecs::With;
+
+    struct Enemy;
+
+    impl Component for Enemy {}
+
+    #[test]
+    fn find_by_name_follows_name_changes() {
+        let mut world = World::new();
+
+        let player = world.create_entity().with(Name::new("player")).build();
+        let door = world.create_entity().with(Name::new("door")).build();
+        let other_door = world.create_entity().with(Name::new("door")).build();
+
+        assert_eq!(world.find_by_name("player"), Some(player));
+        assert_eq!(world.find_by_name("door"), Some(door));
+        assert_eq!(world.find_all_by_name("door"), &[door, other_door]);
+        assert_eq!(world.find_by_name("boss"), None);
+
+        world
+            .insert_component(door, Name::new("boss_door"))
+            .unwrap();
+        assert_eq!(world.find_by_name("boss_door"), Some(door));
+        assert_eq!(world.find_all_by_name("door"), &[other_door]);
+
+        world.remove_component::<Name>(other_door);
+        world.kill_entity(player).unwrap();
+        assert_eq!(world.find_by_name("door"), None);
+        assert_eq!(world.find_by_name("player"), None);
+    }
+
+    #[test]
+    fn zero_sized_tags_can_be_used_as_filters() {
+        let mut world = World::new();
+
+        let enemy = world
+            .create_entity()
+            .with(Name::new("orc"))
+            .with(Enemy)
+            .build();
+        world.create_entity().with(Name::new("villager")).build();
+
+        let enemies = world
+            .query_filtered::<&Name, With<Enemy>>()
+            .unwrap()
+            .map(|(entity, name)| (entity, name.as_str()))
+            .collect::<Vec<_>>();
+
+        assert_eq!(enemies, vec![(enemy, "orc")]);
+    }
+}
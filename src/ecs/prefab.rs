@@ -0,0 +1,575 @@
+use std::collections::{BTreeMap, HashMap};
+use std::time::{Duration, Instant, SystemTime};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::ecs::{push_component, BoxedComponent, Component, Entity, EntityMap, World};
+use crate::error::{PrefabError, SceneError};
+
+/// How often the prefab files are checked for changes by default.
+pub const DEFAULT_PREFAB_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A named set of component values that can be spawned as an entity.
+///
+/// Prefabs can be defined in code using [`PrefabBuilder`], or loaded from a
+/// JSON file that maps prefab names to prefabs:
+///
+/// ```json
+/// {
+///     "orc": { "components": { "Health": 20 } },
+///     "orc_grunt": { "base": "orc", "components": { "Speed": 2.5 } }
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct Prefab {
+    /// The name of the prefab that this prefab inherits its components from.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base: Option<String>,
+
+    /// The components of the prefab, keyed by their registered names.
+    ///
+    /// These replace any components of the same type in the base prefab.
+    #[serde(default)]
+    pub components: BTreeMap<String, Value>,
+}
+
+/// A prefab file that is watched for changes.
+#[derive(Clone)]
+struct PrefabFile {
+    filename: String,
+    modified: Option<SystemTime>,
+
+    /// The names of the prefabs that were defined by the file.
+    names: Vec<String>,
+}
+
+/// All of the prefabs that have been defined, along with the files that they
+/// were loaded from.
+pub(crate) struct Prefabs {
+    prefabs: HashMap<String, Prefab>,
+    files: Vec<PrefabFile>,
+
+    /// How often the files are checked for changes, or `None` if they are not.
+    poll_interval: Option<Duration>,
+
+    /// When the files were last checked for changes.
+    last_polled: Option<Instant>,
+
+    /// The error from the most recent failed reload, if there was one.
+    reload_error: Option<PrefabError>,
+}
+
+impl Prefabs {
+    pub fn new() -> Self {
+        Self {
+            prefabs: HashMap::new(),
+            files: Vec::new(),
+            poll_interval: Some(DEFAULT_PREFAB_POLL_INTERVAL),
+            last_polled: None,
+            reload_error: None,
+        }
+    }
+
+    /// Returns a copy of the prefabs and the files that they are watched in,
+    /// without the state of the most recent reload.
+    pub(crate) fn copy(&self) -> Self {
+        Self {
+            prefabs: self.prefabs.clone(),
+            files: self.files.clone(),
+            poll_interval: self.poll_interval,
+            last_polled: None,
+            reload_error: None,
+        }
+    }
+}
+
+impl World {
+    /// Returns a [`PrefabBuilder`] for defining a prefab with the given name.
+    pub fn create_prefab(&mut self, name: &str) -> PrefabBuilder<'_> {
+        PrefabBuilder::new(self, name)
+    }
+
+    /// Defines a prefab with the given name, replacing any existing prefab with
+    /// the same name.
+    ///
+    /// Returns an error if the prefab contains a component that has not been registered.
+    pub fn insert_prefab(&mut self, name: &str, prefab: Prefab) -> Result<(), PrefabError> {
+        self.validate_prefab(&prefab)?;
+        self.prefabs.prefabs.insert(name.to_string(), prefab);
+        Ok(())
+    }
+
+    /// Returns the prefab with the given name, without any of its inherited components.
+    pub fn prefab(&self, name: &str) -> Option<&Prefab> {
+        self.prefabs.prefabs.get(name)
+    }
+
+    /// Loads all of the prefabs from a JSON prefab file.
+    ///
+    /// The file is watched for changes, and its prefabs are reloaded by
+    /// [`World::reload_prefabs`].
+    pub fn load_prefabs(&mut self, filename: &str) -> Result<(), PrefabError> {
+        let (modified, prefabs) = self.read_prefab_file(filename)?;
+
+        let names = prefabs.keys().cloned().collect();
+        self.prefabs.prefabs.extend(prefabs);
+
+        self.prefabs.files.retain(|file| file.filename != filename);
+        self.prefabs.files.push(PrefabFile {
+            filename: filename.to_string(),
+            modified,
+            names,
+        });
+
+        Ok(())
+    }
+
+    /// Reloads the prefabs from any prefab files that have changed since they
+    /// were last loaded.
+    ///
+    /// If a file fails to load, its previous prefabs are kept and the first
+    /// such error is returned. The file is not loaded again until it changes.
+    pub fn reload_prefabs(&mut self) -> Result<(), PrefabError> {
+        let mut result = Ok(());
+
+        for index in 0..self.prefabs.files.len() {
+            let filename = self.prefabs.files[index].filename.clone();
+
+            let modified = modified_time(&filename);
+            if modified == self.prefabs.files[index].modified {
+                continue;
+            }
+
+            self.prefabs.files[index].modified = modified;
+
+            let prefabs = match self.read_prefab_file(&filename) {
+                Ok((_, prefabs)) => prefabs,
+                Err(err) => {
+                    if result.is_ok() {
+                        result = Err(err);
+                    }
+
+                    continue;
+                }
+            };
+
+            let file = &mut self.prefabs.files[index];
+            for name in file.names.drain(..) {
+                self.prefabs.prefabs.remove(&name);
+            }
+
+            file.names = prefabs.keys().cloned().collect();
+            self.prefabs.prefabs.extend(prefabs);
+        }
+
+        result
+    }
+
+    /// Returns the error from the most recent automatic reload of the prefab
+    /// files, if it failed.
+    pub fn prefab_reload_error(&self) -> Option<&PrefabError> {
+        self.prefabs.reload_error.as_ref()
+    }
+
+    /// Sets how often the prefab files are checked for changes and reloaded,
+    /// or stops checking them if the interval is `None`.
+    ///
+    /// Checking a file reads its metadata from the file system, so release
+    /// builds may want to turn this off. Defaults to
+    /// [`DEFAULT_PREFAB_POLL_INTERVAL`].
+    pub fn set_prefab_poll_interval(&mut self, poll_interval: Option<Duration>) {
+        self.prefabs.poll_interval = poll_interval;
+    }
+
+    /// Reloads the changed prefab files if the poll interval has passed since
+    /// they were last checked, keeping any error for
+    /// [`World::prefab_reload_error`] instead of returning it.
+    ///
+    /// This is run by the engine once per update.
+    pub(crate) fn poll_prefab_files(&mut self, now: Instant) {
+        let poll_interval = match self.prefabs.poll_interval {
+            Some(poll_interval) if !self.prefabs.files.is_empty() => poll_interval,
+            _ => return,
+        };
+
+        if let Some(last_polled) = self.prefabs.last_polled {
+            if now.saturating_duration_since(last_polled) < poll_interval {
+                return;
+            }
+        }
+
+        self.prefabs.last_polled = Some(now);
+
+        let has_changes = self
+            .prefabs
+            .files
+            .iter()
+            .any(|file| modified_time(&file.filename) != file.modified);
+
+        if has_changes {
+            self.prefabs.reload_error = self.reload_prefabs().err();
+        }
+    }
+
+    /// Returns a [`PrefabInstanceBuilder`] for spawning a new entity from the
+    /// prefab with the given name.
+    pub fn spawn_prefab(&mut self, name: &str) -> PrefabInstanceBuilder<'_> {
+        PrefabInstanceBuilder {
+            world: self,
+            name: name.to_string(),
+            overrides: Vec::new(),
+        }
+    }
+
+    /// Spawns an entity with the components of the prefab with the given
+    /// name, other than those replaced by the overrides.
+    ///
+    /// Each component is only inserted once, so its hooks only run once. The
+    /// entities that the prefab's components refer to are entities in this
+    /// world, so they are kept as they are.
+    fn instantiate_prefab(
+        &mut self,
+        name: &str,
+        overrides: Vec<BoxedComponent>,
+    ) -> Result<Entity, PrefabError> {
+        let mut components = self.resolve_prefab(name)?;
+        components.retain(|name, _| {
+            self.registered_component_type(name).is_none_or(|type_id| {
+                !overrides
+                    .iter()
+                    .any(|component| component.component_type() == type_id)
+            })
+        });
+
+        let entity = self.spawn(std::iter::empty());
+
+        let entities = EntityMap::identity();
+        for (name, value) in &components {
+            if let Err(err) = self.insert_serialized_component(entity, name, value, &entities) {
+                let _ = self.kill_entity(entity);
+                return Err(err.into());
+            }
+        }
+
+        for component in overrides {
+            component.attach_to(self, entity);
+        }
+
+        Ok(entity)
+    }
+
+    /// Returns the components of the prefab with the given name, including
+    /// those inherited from its bases.
+    fn resolve_prefab(&self, name: &str) -> Result<BTreeMap<String, Value>, PrefabError> {
+        let mut chain: Vec<&str> = Vec::new();
+
+        let mut current = Some(name);
+        while let Some(name) = current {
+            if chain.contains(&name) {
+                chain.push(name);
+                return Err(PrefabError::InheritanceCycle(chain.join(" -> ")));
+            }
+
+            let prefab = self
+                .prefabs
+                .prefabs
+                .get(name)
+                .ok_or_else(|| PrefabError::UnknownPrefab(name.to_string()))?;
+
+            chain.push(name);
+            current = prefab.base.as_deref();
+        }
+
+        let mut components = BTreeMap::new();
+        for name in chain.into_iter().rev() {
+            components.extend(self.prefabs.prefabs[name].components.clone());
+        }
+
+        Ok(components)
+    }
+
+    fn validate_prefab(&self, prefab: &Prefab) -> Result<(), PrefabError> {
+        for name in prefab.components.keys() {
+            if !self.is_registered_component(name) {
+                return Err(SceneError::UnknownComponent(name.clone()).into());
+            }
+        }
+
+        Ok(())
+    }
+
+    fn read_prefab_file(
+        &self,
+        filename: &str,
+    ) -> Result<(Option<SystemTime>, HashMap<String, Prefab>), PrefabError> {
+        let modified = modified_time(filename);
+
+        let json = std::fs::read_to_string(filename).map_err(|source| PrefabError::Io {
+            filename: filename.to_string(),
+            source,
+        })?;
+
+        let prefabs: HashMap<String, Prefab> =
+            serde_json::from_str(&json).map_err(|source| PrefabError::Malformed {
+                filename: filename.to_string(),
+                source,
+            })?;
+
+        for prefab in prefabs.values() {
+            self.validate_prefab(prefab)?;
+        }
+
+        Ok((modified, prefabs))
+    }
+}
+
+fn modified_time(filename: &str) -> Option<SystemTime> {
+    std::fs::metadata(filename)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+/// A builder for defining a prefab in code.
+pub struct PrefabBuilder<'a> {
+    world: &'a mut World,
+    name: String,
+    prefab: Prefab,
+    error: Option<PrefabError>,
+}
+
+impl<'a> PrefabBuilder<'a> {
+    fn new(world: &'a mut World, name: &str) -> Self {
+        Self {
+            world,
+            name: name.to_string(),
+            prefab: Prefab::default(),
+            error: None,
+        }
+    }
+
+    /// Inherits the components of the prefab with the given name.
+    pub fn base(mut self, base: &str) -> Self {
+        self.prefab.base = Some(base.to_string());
+        self
+    }
+
+    /// Adds a component to the prefab.
+    ///
+    /// The component type must have been registered.
+    pub fn with<T: Component + Serialize>(mut self, component: T) -> Self {
+        match self.world.serialize_registered_component(&component) {
+            Ok((name, value)) => {
+                self.prefab.components.insert(name.to_string(), value);
+            }
+            Err(err) => {
+                self.error.get_or_insert(err.into());
+            }
+        }
+
+        self
+    }
+
+    /// Defines the prefab, replacing any existing prefab with the same name.
+    ///
+    /// Returns an error if any of the components have not been registered.
+    pub fn build(self) -> Result<(), PrefabError> {
+        if let Some(err) = self.error {
+            return Err(err);
+        }
+
+        self.world.insert_prefab(&self.name, self.prefab)
+    }
+}
+
+/// A builder for spawning an entity from a prefab, with components that
+/// override those of the prefab.
+pub struct PrefabInstanceBuilder<'a> {
+    world: &'a mut World,
+    name: String,
+    overrides: Vec<BoxedComponent>,
+}
+
+impl<'a> PrefabInstanceBuilder<'a> {
+    /// Adds a component to the entity, replacing the prefab's component of the
+    /// same type, if it has one.
+    pub fn with<T: Component>(mut self, component: T) -> Self {
+        push_component(&mut self.overrides, Box::new(component));
+        self
+    }
+
+    /// Spawns the entity.
+    ///
+    /// Returns an error if the prefab, or one of its bases, does not exist.
+    pub fn build(self) -> Result<Entity, PrefabError> {
+        self.world.instantiate_prefab(&self.name, self.overrides)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::{Arc, Mutex};
+
+    use crate::ecs::test_util::{self, Health, Target};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Speed(f32);
+
+    impl Component for Speed {}
+
+    fn registered_world() -> World {
+        let mut world = test_util::registered_world();
+        world.register_component::<Speed>("Speed");
+        world
+    }
+
+    #[test]
+    fn prefabs_inherit_from_their_base() {
+        let mut world = registered_world();
+
+        world
+            .create_prefab("orc")
+            .with(Health(20))
+            .with(Speed(1.0))
+            .build()
+            .unwrap();
+        world
+            .create_prefab("orc_grunt")
+            .base("orc")
+            .with(Speed(2.5))
+            .build()
+            .unwrap();
+
+        let grunt = world.spawn_prefab("orc_grunt").build().unwrap();
+        assert_eq!(world.get_component::<Health>(grunt), Some(&Health(20)));
+        assert_eq!(world.get_component::<Speed>(grunt), Some(&Speed(2.5)));
+
+        let wounded = world
+            .spawn_prefab("orc_grunt")
+            .with(Health(5))
+            .build()
+            .unwrap();
+        assert_eq!(world.get_component::<Health>(wounded), Some(&Health(5)));
+    }
+
+    #[test]
+    fn overridden_prefab_components_are_only_added_once() {
+        let mut world = registered_world();
+
+        let added = Arc::new(Mutex::new(Vec::new()));
+        let log = Arc::clone(&added);
+        world.on_add::<Health>(move |_, health, _| log.lock().unwrap().push(health.0));
+
+        world
+            .create_prefab("orc")
+            .with(Health(20))
+            .with(Speed(1.0))
+            .build()
+            .unwrap();
+
+        let wounded = world.spawn_prefab("orc").with(Health(5)).build().unwrap();
+        assert_eq!(world.get_component::<Health>(wounded), Some(&Health(5)));
+        assert_eq!(world.get_component::<Speed>(wounded), Some(&Speed(1.0)));
+        assert_eq!(*added.lock().unwrap(), vec![5]);
+    }
+
+    #[test]
+    fn prefabs_keep_references_to_entities_in_the_world() {
+        let mut world = registered_world();
+        let player = world.create_entity().build();
+
+        world
+            .create_prefab("hunter")
+            .with(Target(player))
+            .build()
+            .unwrap();
+
+        let hunter = world.spawn_prefab("hunter").build().unwrap();
+        assert_eq!(world.get_component::<Target>(hunter), Some(&Target(player)));
+    }
+
+    #[test]
+    fn missing_and_cyclic_prefabs_are_reported() {
+        let mut world = registered_world();
+
+        world.create_prefab("a").base("b").build().unwrap();
+        world.create_prefab("b").base("a").build().unwrap();
+        world.create_prefab("c").base("missing").build().unwrap();
+
+        match world.spawn_prefab("a").build() {
+            Err(PrefabError::InheritanceCycle(cycle)) => assert_eq!(cycle, "a -> b -> a"),
+            result => panic!("expected an inheritance cycle, got {:?}", result),
+        }
+        match world.spawn_prefab("c").build() {
+            Err(PrefabError::UnknownPrefab(name)) => assert_eq!(name, "missing"),
+            result => panic!("expected an unknown prefab, got {:?}", result),
+        }
+        assert!(world.entities().is_empty());
+    }
+
+    #[test]
+    fn prefab_files_are_reloaded_when_they_change() {
+        let path =
+            std::env::temp_dir().join(format!("peacock-prefabs-{}.json", std::process::id()));
+        let filename = path.to_str().unwrap();
+
+        let write = |json: &str, modified: SystemTime| {
+            std::fs::write(&path, json).unwrap();
+            std::fs::File::options()
+                .write(true)
+                .open(&path)
+                .unwrap()
+                .set_modified(modified)
+                .unwrap();
+        };
+
+        let mut world = registered_world();
+        let mut polled = Instant::now();
+
+        let now = SystemTime::now();
+        write(r#"{ "orc": { "components": { "Health": 20 } } }"#, now);
+        world.load_prefabs(filename).unwrap();
+
+        write(
+            r#"{ "orc": { "components": { "Health": 30, "Mana": 1 } } }"#,
+            now + Duration::from_secs(1),
+        );
+        polled += DEFAULT_PREFAB_POLL_INTERVAL;
+        world.poll_prefab_files(polled);
+        assert!(world.prefab_reload_error().is_some());
+
+        let orc = world.spawn_prefab("orc").build().unwrap();
+        assert_eq!(world.get_component::<Health>(orc), Some(&Health(20)));
+
+        write(
+            r#"{ "orc": { "components": { "Health": 40 } } }"#,
+            now + Duration::from_secs(2),
+        );
+
+        // The files are not checked again until the poll interval has passed.
+        world.poll_prefab_files(polled);
+        assert!(world.prefab_reload_error().is_some());
+
+        polled += DEFAULT_PREFAB_POLL_INTERVAL;
+        world.poll_prefab_files(polled);
+        assert!(world.prefab_reload_error().is_none());
+
+        let orc = world.spawn_prefab("orc").build().unwrap();
+        assert_eq!(world.get_component::<Health>(orc), Some(&Health(40)));
+
+        write(
+            r#"{ "orc": { "components": { "Health": 50 } } }"#,
+            now + Duration::from_secs(3),
+        );
+        world.set_prefab_poll_interval(None);
+        polled += DEFAULT_PREFAB_POLL_INTERVAL;
+        world.poll_prefab_files(polled);
+
+        let orc = world.spawn_prefab("orc").build().unwrap();
+        assert_eq!(world.get_component::<Health>(orc), Some(&Health(40)));
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
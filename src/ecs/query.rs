@@ -0,0 +1,486 @@
+use std::any::{type_name, TypeId};
+use std::marker::PhantomData;
+use std::ptr::NonNull;
+
+use crate::ecs::{Component, ComponentTicks, Entity, SparseSet, World};
+use crate::error::EcsError;
+
+/// The set of component or resource types accessed by a query or a system.
+#[derive(Debug, Default, Clone)]
+pub struct Access {
+    reads: Vec<(TypeId, &'static str)>,
+    writes: Vec<(TypeId, &'static str)>,
+}
+
+impl Access {
+    pub(crate) fn read<T: 'static>(&mut self) {
+        self.reads.push((TypeId::of::<T>(), type_name::<T>()));
+    }
+
+    pub(crate) fn write<T: 'static>(&mut self) {
+        self.writes.push((TypeId::of::<T>(), type_name::<T>()));
+    }
+
+    pub(crate) fn is_read(&self, type_id: TypeId) -> bool {
+        self.reads.iter().any(|(other, _)| *other == type_id)
+    }
+
+    pub(crate) fn is_written(&self, type_id: TypeId) -> bool {
+        self.writes.iter().any(|(other, _)| *other == type_id)
+    }
+
+    /// Ensures that no component type is accessed mutably while also being
+    /// accessed elsewhere in the same query.
+    fn validate(&self) -> Result<(), EcsError> {
+        for (index, (component_type, name)) in self.writes.iter().enumerate() {
+            let written_twice = self.writes[index + 1..]
+                .iter()
+                .any(|(other, _)| other == component_type);
+
+            if written_twice || self.is_read(*component_type) {
+                return Err(EcsError::ConflictingAccess(name));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the name of a type that one of the accesses writes while the
+    /// other accesses it, if there is one.
+    pub(crate) fn conflict_with(&self, other: &Access) -> Option<&'static str> {
+        let conflict = |writes: &[(TypeId, &'static str)], accesses: &Access| {
+            writes
+                .iter()
+                .find(|(type_id, _)| accesses.is_read(*type_id) || accesses.is_written(*type_id))
+                .map(|(_, name)| *name)
+        };
+
+        conflict(&self.writes, other).or_else(|| conflict(&other.writes, self))
+    }
+
+    /// Returns the name of a type that this access uses without it being
+    /// covered by the given declared access, if there is one.
+    pub(crate) fn undeclared_in(&self, declared: &Access) -> Option<&'static str> {
+        let undeclared_read = self
+            .reads
+            .iter()
+            .find(|(type_id, _)| !declared.is_read(*type_id) && !declared.is_written(*type_id));
+        let undeclared_write = self
+            .writes
+            .iter()
+            .find(|(type_id, _)| !declared.is_written(*type_id));
+
+        undeclared_read.or(undeclared_write).map(|(_, name)| *name)
+    }
+}
+
+/// The storages of a [`World`], from which the state of a query is initialized.
+#[doc(hidden)]
+pub trait Storages {
+    fn storage<T: Component>(&mut self) -> Option<NonNull<SparseSet<T>>>;
+
+    fn change_tick(&self) -> u32;
+}
+
+impl Storages for World {
+    fn storage<T: Component>(&mut self) -> Option<NonNull<SparseSet<T>>> {
+        self.storage_mut::<T>().map(NonNull::from)
+    }
+
+    fn change_tick(&self) -> u32 {
+        self.change_tick
+    }
+}
+
+/// A set of components that can be fetched for an entity.
+///
+/// Implemented for `&T`, `&mut T`, `Option<Q>` and tuples of queries.
+pub trait Query {
+    /// The value yielded for each matching entity.
+    type Item<'w>;
+
+    #[doc(hidden)]
+    type State;
+
+    #[doc(hidden)]
+    fn access(access: &mut Access);
+
+    #[doc(hidden)]
+    fn init<S: Storages>(world: &mut S) -> Self::State;
+
+    /// Returns the entities that could possibly match the query, or `None` if
+    /// the query does not narrow down the entities.
+    ///
+    /// # Safety
+    ///
+    /// The world that the state was initialized from must still be alive.
+    #[doc(hidden)]
+    unsafe fn candidates<'w>(state: &Self::State) -> Option<&'w [Entity]>;
+
+    /// # Safety
+    ///
+    /// The caller must ensure that the access of the query has been validated
+    /// and that each entity is fetched at most once for the lifetime `'w`.
+    #[doc(hidden)]
+    unsafe fn fetch<'w>(state: &Self::State, entity: Entity) -> Option<Self::Item<'w>>;
+}
+
+/// A condition that an entity must satisfy to be included in a query.
+///
+/// Implemented for [`With`], [`Without`], [`Added`], [`Changed`] and tuples of filters.
+pub trait QueryFilter {
+    #[doc(hidden)]
+    type State;
+
+    #[doc(hidden)]
+    fn access(access: &mut Access);
+
+    #[doc(hidden)]
+    fn init<S: Storages>(world: &mut S) -> Self::State;
+
+    /// # Safety
+    ///
+    /// The world that the state was initialized from must still be alive.
+    #[doc(hidden)]
+    unsafe fn matches(state: &Self::State, entity: Entity) -> bool;
+}
+
+unsafe fn contains<T: Component>(storage: Option<NonNull<SparseSet<T>>>, entity: Entity) -> bool {
+    storage.is_some_and(|storage| storage.as_ref().contains(entity))
+}
+
+unsafe fn entities_of<'w, T: Component>(
+    storage: Option<NonNull<SparseSet<T>>>,
+) -> Option<&'w [Entity]> {
+    // If there is no storage then no entities can match.
+    Some(storage.map_or(&[], |storage| storage.as_ref().entities()))
+}
+
+impl<T: Component> Query for &T {
+    type Item<'w> = &'w T;
+    type State = Option<NonNull<SparseSet<T>>>;
+
+    fn access(access: &mut Access) {
+        access.read::<T>();
+    }
+
+    fn init<S: Storages>(world: &mut S) -> Self::State {
+        world.storage::<T>()
+    }
+
+    unsafe fn candidates<'w>(state: &Self::State) -> Option<&'w [Entity]> {
+        entities_of(*state)
+    }
+
+    unsafe fn fetch<'w>(state: &Self::State, entity: Entity) -> Option<&'w T> {
+        (*state)?.as_ref().get(entity)
+    }
+}
+
+/// The storage of a component type that is fetched mutably.
+///
+/// The components and their ticks are written through pointers that are taken
+/// once, when the query is created. Reborrowing the whole storage mutably for
+/// each entity would invalidate the components that were already fetched.
+#[doc(hidden)]
+pub struct MutStorage<T> {
+    storage: NonNull<SparseSet<T>>,
+    data: *mut T,
+    ticks: *mut ComponentTicks,
+}
+
+impl<T: Component> Query for &mut T {
+    type Item<'w> = &'w mut T;
+    type State = (Option<MutStorage<T>>, u32);
+
+    fn access(access: &mut Access) {
+        access.write::<T>();
+    }
+
+    fn init<S: Storages>(world: &mut S) -> Self::State {
+        let storage = world.storage::<T>().map(|mut storage| {
+            // SAFETY: The storage was just returned by the world, and no
+            // components have been fetched from it yet.
+            let (data, ticks) = unsafe { storage.as_mut().data_and_ticks_ptr() };
+            MutStorage {
+                storage,
+                data,
+                ticks,
+            }
+        });
+
+        (storage, world.change_tick())
+    }
+
+    unsafe fn candidates<'w>((storage, _): &Self::State) -> Option<&'w [Entity]> {
+        entities_of(storage.as_ref().map(|storage| storage.storage))
+    }
+
+    unsafe fn fetch<'w>((storage, tick): &Self::State, entity: Entity) -> Option<&'w mut T> {
+        let storage = storage.as_ref()?;
+
+        // Only the sparse index is read through the storage itself, which does
+        // not overlap with the components that have been handed out.
+        let index = storage.storage.as_ref().index_of(entity)?;
+
+        (*storage.ticks.add(index)).changed = *tick;
+        Some(&mut *storage.data.add(index))
+    }
+}
+
+impl<Q: Query> Query for Option<Q> {
+    type Item<'w> = Option<Q::Item<'w>>;
+    type State = Q::State;
+
+    fn access(access: &mut Access) {
+        Q::access(access);
+    }
+
+    fn init<S: Storages>(world: &mut S) -> Self::State {
+        Q::init(world)
+    }
+
+    unsafe fn candidates<'w>(_state: &Self::State) -> Option<&'w [Entity]> {
+        None
+    }
+
+    unsafe fn fetch<'w>(state: &Self::State, entity: Entity) -> Option<Self::Item<'w>> {
+        Some(Q::fetch(state, entity))
+    }
+}
+
+/// A [`QueryFilter`] that only matches entities that have a component of type `T`.
+pub struct With<T>(PhantomData<T>);
+
+impl<T: Component> QueryFilter for With<T> {
+    type State = Option<NonNull<SparseSet<T>>>;
+
+    fn access(access: &mut Access) {
+        access.read::<T>();
+    }
+
+    fn init<S: Storages>(world: &mut S) -> Self::State {
+        world.storage::<T>()
+    }
+
+    unsafe fn matches(state: &Self::State, entity: Entity) -> bool {
+        contains(*state, entity)
+    }
+}
+
+/// A [`QueryFilter`] that only matches entities that do not have a component of type `T`.
+pub struct Without<T>(PhantomData<T>);
+
+impl<T: Component> QueryFilter for Without<T> {
+    type State = Option<NonNull<SparseSet<T>>>;
+
+    fn access(access: &mut Access) {
+        access.read::<T>();
+    }
+
+    fn init<S: Storages>(world: &mut S) -> Self::State {
+        world.storage::<T>()
+    }
+
+    unsafe fn matches(state: &Self::State, entity: Entity) -> bool {
+        !contains(*state, entity)
+    }
+}
+
+/// A [`QueryFilter`] that only matches entities whose component of type `T`
+/// was added during the current tick.
+pub struct Added<T>(PhantomData<T>);
+
+impl<T: Component> QueryFilter for Added<T> {
+    type State = (Option<NonNull<SparseSet<T>>>, u32);
+
+    fn access(access: &mut Access) {
+        access.read::<T>();
+    }
+
+    fn init<S: Storages>(world: &mut S) -> Self::State {
+        (world.storage::<T>(), world.change_tick())
+    }
+
+    unsafe fn matches((storage, tick): &Self::State, entity: Entity) -> bool {
+        storage
+            .and_then(|storage| storage.as_ref().ticks(entity))
+            .is_some_and(|ticks| ticks.is_added(*tick))
+    }
+}
+
+/// A [`QueryFilter`] that only matches entities whose component of type `T`
+/// was added or mutably accessed during the current tick.
+pub struct Changed<T>(PhantomData<T>);
+
+impl<T: Component> QueryFilter for Changed<T> {
+    type State = (Option<NonNull<SparseSet<T>>>, u32);
+
+    fn access(access: &mut Access) {
+        access.read::<T>();
+    }
+
+    fn init<S: Storages>(world: &mut S) -> Self::State {
+        (world.storage::<T>(), world.change_tick())
+    }
+
+    unsafe fn matches((storage, tick): &Self::State, entity: Entity) -> bool {
+        storage
+            .and_then(|storage| storage.as_ref().ticks(entity))
+            .is_some_and(|ticks| ticks.is_changed(*tick))
+    }
+}
+
+macro_rules! impl_query_for_tuple {
+    ( $( $name:ident ),* ) => {
+        #[allow(non_snake_case, unused_variables, clippy::unused_unit)]
+        impl<$( $name: Query ),*> Query for ( $( $name, )* ) {
+            type Item<'w> = ( $( $name::Item<'w>, )* );
+            type State = ( $( $name::State, )* );
+
+            fn access(access: &mut Access) {
+                $( $name::access(access); )*
+            }
+
+            fn init<S: Storages>(world: &mut S) -> Self::State {
+                ( $( $name::init(world), )* )
+            }
+
+            unsafe fn candidates<'w>(state: &Self::State) -> Option<&'w [Entity]> {
+                let ( $( $name, )* ) = state;
+                let candidates: Option<&'w [Entity]> = None;
+                $(
+                    let candidates = match (candidates, $name::candidates($name)) {
+                        (Some(current), Some(other)) if other.len() < current.len() => Some(other),
+                        (None, other) => other,
+                        (current, _) => current,
+                    };
+                )*
+                candidates
+            }
+
+            unsafe fn fetch<'w>(state: &Self::State, entity: Entity) -> Option<Self::Item<'w>> {
+                let ( $( $name, )* ) = state;
+                Some(( $( $name::fetch($name, entity)?, )* ))
+            }
+        }
+
+        #[allow(non_snake_case, unused_variables, clippy::unused_unit)]
+        impl<$( $name: QueryFilter ),*> QueryFilter for ( $( $name, )* ) {
+            type State = ( $( $name::State, )* );
+
+            fn access(access: &mut Access) {
+                $( $name::access(access); )*
+            }
+
+            fn init<S: Storages>(world: &mut S) -> Self::State {
+                ( $( $name::init(world), )* )
+            }
+
+            unsafe fn matches(state: &Self::State, entity: Entity) -> bool {
+                let ( $( $name, )* ) = state;
+                true $( && $name::matches($name, entity) )*
+            }
+        }
+    };
+}
+
+impl_query_for_tuple!();
+impl_query_for_tuple!(A);
+impl_query_for_tuple!(A, B);
+impl_query_for_tuple!(A, B, C);
+impl_query_for_tuple!(A, B, C, D);
+impl_query_for_tuple!(A, B, C, D, E);
+impl_query_for_tuple!(A, B, C, D, E, F);
+impl_query_for_tuple!(A, B, C, D, E, F, G);
+impl_query_for_tuple!(A, B, C, D, E, F, G, H);
+
+/// An iterator over the entities matching a [`Query`] and a [`QueryFilter`].
+pub struct QueryIter<'w, Q: Query, F: QueryFilter = ()> {
+    entities: std::slice::Iter<'w, Entity>,
+    state: Q::State,
+    filter_state: F::State,
+}
+
+impl<'w, Q: Query, F: QueryFilter> QueryIter<'w, Q, F> {
+    pub(crate) fn new(world: &'w mut World) -> Result<Self, EcsError> {
+        let (access, _) = Self::access();
+        access.validate()?;
+
+        let state = Q::init(world);
+        let filter_state = F::init(world);
+
+        let world: &'w World = world;
+
+        // SAFETY: The storages are owned by the world, which we are borrowing for `'w`.
+        Ok(unsafe { Self::from_state(state, filter_state, &world.entities) })
+    }
+
+    /// Creates an iterator from storages that are borrowed elsewhere, such as
+    /// by a system that is running in parallel with other systems.
+    ///
+    /// # Safety
+    ///
+    /// The storages must remain valid for `'w`, and must not be accessed
+    /// elsewhere in a way that conflicts with the access of the query.
+    pub(crate) unsafe fn from_storages<S: Storages>(
+        storages: &mut S,
+        entities: &'w [Entity],
+    ) -> Result<Self, EcsError> {
+        let (access, _) = Self::access();
+        access.validate()?;
+
+        let state = Q::init(storages);
+        let filter_state = F::init(storages);
+
+        Ok(Self::from_state(state, filter_state, entities))
+    }
+
+    /// Returns the access of the query and of its filter.
+    pub(crate) fn access() -> (Access, Access) {
+        let mut access = Access::default();
+        Q::access(&mut access);
+
+        let mut filter_access = Access::default();
+        F::access(&mut filter_access);
+
+        (access, filter_access)
+    }
+
+    /// # Safety
+    ///
+    /// The storages in the states must remain valid for `'w`.
+    unsafe fn from_state(state: Q::State, filter_state: F::State, entities: &'w [Entity]) -> Self {
+        // Only visit the entities in the smallest storage that the query requires,
+        // rather than every entity in the world.
+        let entities = Q::candidates(&state).unwrap_or(entities);
+
+        Self {
+            entities: entities.iter(),
+            state,
+            filter_state,
+        }
+    }
+}
+
+impl<'w, Q: Query, F: QueryFilter> Iterator for QueryIter<'w, Q, F> {
+    type Item = (Entity, Q::Item<'w>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for &entity in self.entities.by_ref() {
+            // SAFETY: The access of the query was validated when the iterator
+            // was created, and each entity only appears once in a storage.
+            unsafe {
+                if !F::matches(&self.filter_state, entity) {
+                    continue;
+                }
+
+                if let Some(item) = Q::fetch(&self.state, entity) {
+                    return Some((entity, item));
+                }
+            }
+        }
+
+        None
+    }
+}
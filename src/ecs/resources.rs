@@ -0,0 +1,65 @@
+use std::any::{type_name, Any, TypeId};
+use std::collections::HashMap;
+use std::ptr::NonNull;
+
+use crate::error::EcsError;
+
+/// A globally unique value stored in the [`World`](crate::ecs::World), such as
+/// the score or a random number generator.
+///
+/// Implemented for all types that are `Send + Sync + 'static`.
+pub trait Resource: Send + Sync + 'static {}
+
+impl<T: Send + Sync + 'static> Resource for T {}
+
+#[derive(Default)]
+pub(crate) struct Resources {
+    resources: HashMap<TypeId, Box<dyn Any + Send + Sync>>,
+}
+
+impl Resources {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert<T: Resource>(&mut self, resource: T) -> Option<T> {
+        self.resources
+            .insert(TypeId::of::<T>(), Box::new(resource))
+            .and_then(|previous| previous.downcast::<T>().ok())
+            .map(|previous| *previous)
+    }
+
+    pub fn contains<T: Resource>(&self) -> bool {
+        self.resources.contains_key(&TypeId::of::<T>())
+    }
+
+    pub fn get<T: Resource>(&self) -> Result<&T, EcsError> {
+        self.resources
+            .get(&TypeId::of::<T>())
+            .and_then(|resource| resource.downcast_ref::<T>())
+            .ok_or_else(|| EcsError::MissingResource(type_name::<T>()))
+    }
+
+    pub fn get_mut<T: Resource>(&mut self) -> Result<&mut T, EcsError> {
+        self.resources
+            .get_mut(&TypeId::of::<T>())
+            .and_then(|resource| resource.downcast_mut::<T>())
+            .ok_or_else(|| EcsError::MissingResource(type_name::<T>()))
+    }
+
+    /// Returns pointers to all of the resources, so that they can be shared
+    /// between systems running in parallel.
+    pub fn pointers(&mut self) -> HashMap<TypeId, NonNull<dyn Any + Send + Sync>> {
+        self.resources
+            .iter_mut()
+            .map(|(&type_id, resource)| (type_id, NonNull::from(&mut **resource)))
+            .collect()
+    }
+
+    pub fn remove<T: Resource>(&mut self) -> Option<T> {
+        self.resources
+            .remove(&TypeId::of::<T>())
+            .and_then(|resource| resource.downcast::<T>().ok())
+            .map(|resource| *resource)
+    }
+}
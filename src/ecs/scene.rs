@@ -0,0 +1,548 @@
+use std::any::TypeId;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::ecs::{Component, Entity, Name, Parent, Transform, World};
+use crate::error::SceneError;
+
+/// A collection of entities and their registered components, in a form that
+/// can be saved to and loaded from a file.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct Scene {
+    /// The entities in the scene.
+    pub entities: Vec<SceneEntity>,
+}
+
+impl Scene {
+    /// Parses a [`Scene`] from JSON.
+    pub fn from_json(json: &str) -> Result<Self, SceneError> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    /// Returns the scene as pretty-printed JSON.
+    pub fn to_json(&self) -> Result<String, SceneError> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+}
+
+/// An entity in a [`Scene`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SceneEntity {
+    /// The entity as it was when the scene was saved.
+    ///
+    /// This is only used to identify the entity within the scene, and is
+    /// replaced by a new entity when the scene is loaded.
+    pub entity: Entity,
+
+    /// The components of the entity, keyed by their registered names.
+    #[serde(default)]
+    pub components: BTreeMap<String, Value>,
+}
+
+/// A mapping from the entities in a [`Scene`] to the entities that were
+/// created for them when it was loaded, or from moved entities to the
+/// entities that replaced them in their new world.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct EntityMap {
+    entities: HashMap<Entity, Entity>,
+
+    /// Whether entities that are not in the map map to themselves, rather
+    /// than being an error.
+    is_identity: bool,
+}
+
+impl EntityMap {
+    /// Returns the entity that was created for the given scene entity.
+    ///
+    /// Returns an error if the entity is not part of the scene.
+    pub fn get(&self, entity: Entity) -> Result<Entity, SceneError> {
+        match self.entities.get(&entity) {
+            Some(&mapped) => Ok(mapped),
+            None if self.is_identity => Ok(entity),
+            None => Err(SceneError::UnmappedEntity(entity)),
+        }
+    }
+
+    /// Returns a map that maps every entity to itself, for components that
+    /// refer to entities in the world that they are inserted into.
+    pub(crate) fn identity() -> Self {
+        Self {
+            entities: HashMap::new(),
+            is_identity: true,
+        }
+    }
+
+    pub(crate) fn insert(&mut self, from: Entity, to: Entity) {
+        self.entities.insert(from, to);
+    }
+
+    /// Returns the number of entities in the map.
+    pub fn len(&self) -> usize {
+        self.entities.len()
+    }
+
+    /// Returns whether the map contains no entities.
+    pub fn is_empty(&self) -> bool {
+        self.entities.is_empty()
+    }
+
+    /// Returns an iterator over the scene entities and the entities that were
+    /// created for them.
+    pub fn iter(&self) -> impl Iterator<Item = (Entity, Entity)> + '_ {
+        self.entities.iter().map(|(&from, &to)| (from, to))
+    }
+}
+
+/// A component that refers to other entities, and needs those references to
+/// be updated when it is loaded from a [`Scene`] or moved to another world.
+pub trait MapEntities {
+    /// Replaces every entity referenced by this component with the entity it
+    /// maps to.
+    fn map_entities(&mut self, entities: &EntityMap) -> Result<(), SceneError>;
+}
+
+type SerializeFn = fn(&World, Entity) -> Option<serde_json::Result<Value>>;
+type InsertFn = fn(&mut World, Entity, &str, &Value, &EntityMap) -> Result<(), SceneError>;
+type MapFn = fn(&mut World, Entity, &EntityMap) -> Result<(), SceneError>;
+
+/// The functions for remapping the entities referenced by a component type.
+#[derive(Clone, Copy)]
+pub(crate) struct EntityMapper {
+    /// Remaps the entities referenced by the component of an entity.
+    pub(crate) map: MapFn,
+
+    /// Returns an error if the component of an entity refers to an entity
+    /// that is not in the map, which must map every entity to itself. The
+    /// component and its change ticks are left as they were.
+    pub(crate) check: MapFn,
+}
+
+#[derive(Clone)]
+struct ComponentRegistration {
+    name: &'static str,
+    type_id: TypeId,
+    serialize: SerializeFn,
+    insert: InsertFn,
+}
+
+/// The component types that can be saved to and loaded from a [`Scene`].
+#[derive(Clone)]
+pub(crate) struct ComponentRegistry {
+    registrations: Vec<ComponentRegistration>,
+
+    /// The component types that refer to other entities.
+    mappers: HashMap<TypeId, EntityMapper>,
+}
+
+impl Default for ComponentRegistry {
+    fn default() -> Self {
+        let mut registry = Self {
+            registrations: Vec::new(),
+            mappers: HashMap::new(),
+        };
+
+        registry.register::<Transform>(
+            "Transform",
+            serialize_component::<Transform>,
+            insert_component::<Transform>,
+        );
+        registry.register::<Parent>("Parent", serialize_parent, insert_parent);
+        registry.register::<Name>(
+            "Name",
+            serialize_component::<Name>,
+            insert_component::<Name>,
+        );
+
+        registry
+    }
+}
+
+impl ComponentRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a component type under the given name, replacing any
+    /// existing registration for the same name or type.
+    pub fn register<T: Component>(
+        &mut self,
+        name: &'static str,
+        serialize: SerializeFn,
+        insert: InsertFn,
+    ) {
+        let type_id = TypeId::of::<T>();
+
+        self.registrations
+            .retain(|registration| registration.name != name && registration.type_id != type_id);
+
+        self.registrations.push(ComponentRegistration {
+            name,
+            type_id,
+            serialize,
+            insert,
+        });
+    }
+
+    /// Returns the functions that remap the entities referenced by each
+    /// component type that implements [`MapEntities`].
+    pub(crate) fn mappers(&self) -> impl Iterator<Item = EntityMapper> + '_ {
+        self.mappers.values().copied()
+    }
+
+    fn get(&self, name: &str) -> Option<&ComponentRegistration> {
+        self.registrations
+            .iter()
+            .find(|registration| registration.name == name)
+    }
+
+    fn name_of(&self, type_id: TypeId) -> Option<&'static str> {
+        self.registrations
+            .iter()
+            .find(|registration| registration.type_id == type_id)
+            .map(|registration| registration.name)
+    }
+}
+
+impl World {
+    /// Registers a component type so that it is included in saved scenes and
+    /// can be loaded from them under the given name.
+    pub fn register_component<T>(&mut self, name: &'static str)
+    where
+        T: Component + Serialize + DeserializeOwned,
+    {
+        self.registry
+            .register::<T>(name, serialize_component::<T>, insert_component::<T>);
+        self.registry.mappers.remove(&TypeId::of::<T>());
+    }
+
+    /// Registers a component type that refers to other entities, so that those
+    /// references are remapped when it is loaded from a scene.
+    pub fn register_component_with_entities<T>(&mut self, name: &'static str)
+    where
+        T: Component + Serialize + DeserializeOwned + MapEntities,
+    {
+        self.registry
+            .register::<T>(name, serialize_component::<T>, insert_mapped_component::<T>);
+        self.registry.mappers.insert(
+            TypeId::of::<T>(),
+            EntityMapper {
+                map: map_component::<T>,
+                check: check_component::<T>,
+            },
+        );
+    }
+
+    /// Returns a [`Scene`] containing all of the entities in the world, along
+    /// with their registered components.
+    ///
+    /// Components that have not been registered are left out of the scene.
+    pub fn save_scene(&self) -> Result<Scene, SceneError> {
+        let mut entities = Vec::with_capacity(self.entities.len());
+
+        for &entity in &self.entities {
+            let mut components = BTreeMap::new();
+
+            for registration in &self.registry.registrations {
+                if let Some(value) = (registration.serialize)(self, entity) {
+                    let value = value.map_err(|source| SceneError::InvalidComponent {
+                        component: registration.name.to_string(),
+                        source,
+                    })?;
+
+                    components.insert(registration.name.to_string(), value);
+                }
+            }
+
+            entities.push(SceneEntity { entity, components });
+        }
+
+        Ok(Scene { entities })
+    }
+
+    /// Creates a new entity for every entity in the given [`Scene`], returning
+    /// the mapping from the scene entities to the new entities.
+    ///
+    /// Returns an error if the scene contains a component that has not been
+    /// registered, or a component that could not be loaded. No entities are
+    /// added to the world if loading fails.
+    pub fn load_scene(&mut self, scene: &Scene) -> Result<EntityMap, SceneError> {
+        let mut seen = HashSet::with_capacity(scene.entities.len());
+
+        for scene_entity in &scene.entities {
+            if !seen.insert(scene_entity.entity) {
+                return Err(SceneError::DuplicateEntity(scene_entity.entity));
+            }
+
+            for name in scene_entity.components.keys() {
+                if !self.is_registered_component(name) {
+                    return Err(SceneError::UnknownComponent(name.clone()));
+                }
+            }
+        }
+
+        let mut map = EntityMap::default();
+        for scene_entity in &scene.entities {
+            let entity = self.spawn(std::iter::empty());
+            map.entities.insert(scene_entity.entity, entity);
+        }
+
+        if let Err(err) = self.insert_scene_components(scene, &map) {
+            for (_, entity) in map.iter() {
+                let _ = self.kill_entity(entity);
+            }
+
+            return Err(err);
+        }
+
+        Ok(map)
+    }
+
+    fn insert_scene_components(
+        &mut self,
+        scene: &Scene,
+        map: &EntityMap,
+    ) -> Result<(), SceneError> {
+        for scene_entity in &scene.entities {
+            let entity = map.get(scene_entity.entity)?;
+
+            for (name, value) in &scene_entity.components {
+                self.insert_serialized_component(entity, name, value, map)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns whether a component type has been registered under the given name.
+    pub(crate) fn is_registered_component(&self, name: &str) -> bool {
+        self.registry.get(name).is_some()
+    }
+
+    /// Returns the type of the component registered under the given name.
+    pub(crate) fn registered_component_type(&self, name: &str) -> Option<TypeId> {
+        self.registry
+            .get(name)
+            .map(|registration| registration.type_id)
+    }
+
+    /// Serializes a component, returning it along with its registered name.
+    pub(crate) fn serialize_registered_component<T: Component + Serialize>(
+        &self,
+        component: &T,
+    ) -> Result<(&'static str, Value), SceneError> {
+        let name = self
+            .registry
+            .name_of(TypeId::of::<T>())
+            .ok_or_else(|| SceneError::UnregisteredComponent(std::any::type_name::<T>()))?;
+
+        let value =
+            serde_json::to_value(component).map_err(|source| SceneError::InvalidComponent {
+                component: name.to_string(),
+                source,
+            })?;
+
+        Ok((name, value))
+    }
+
+    /// Deserializes the component registered under the given name and inserts
+    /// it on the given entity.
+    pub(crate) fn insert_serialized_component(
+        &mut self,
+        entity: Entity,
+        name: &str,
+        value: &Value,
+        map: &EntityMap,
+    ) -> Result<(), SceneError> {
+        let insert = self
+            .registry
+            .get(name)
+            .ok_or_else(|| SceneError::UnknownComponent(name.to_string()))?
+            .insert;
+
+        insert(self, entity, name, value, map)
+    }
+}
+
+fn deserialize_component<T: DeserializeOwned>(name: &str, value: &Value) -> Result<T, SceneError> {
+    T::deserialize(value).map_err(|source| SceneError::InvalidComponent {
+        component: name.to_string(),
+        source,
+    })
+}
+
+fn serialize_component<T: Component + Serialize>(
+    world: &World,
+    entity: Entity,
+) -> Option<serde_json::Result<Value>> {
+    world.get_component::<T>(entity).map(serde_json::to_value)
+}
+
+fn insert_component<T: Component + DeserializeOwned>(
+    world: &mut World,
+    entity: Entity,
+    name: &str,
+    value: &Value,
+    _entities: &EntityMap,
+) -> Result<(), SceneError> {
+    let component = deserialize_component::<T>(name, value)?;
+    world.attach_component(entity, component);
+    Ok(())
+}
+
+fn insert_mapped_component<T: Component + DeserializeOwned + MapEntities>(
+    world: &mut World,
+    entity: Entity,
+    name: &str,
+    value: &Value,
+    entities: &EntityMap,
+) -> Result<(), SceneError> {
+    let mut component = deserialize_component::<T>(name, value)?;
+    component.map_entities(entities)?;
+    world.attach_component(entity, component);
+    Ok(())
+}
+
+fn map_component<T: Component + MapEntities>(
+    world: &mut World,
+    entity: Entity,
+    entities: &EntityMap,
+) -> Result<(), SceneError> {
+    match world.get_component_mut::<T>(entity) {
+        Some(component) => component.map_entities(entities),
+        None => Ok(()),
+    }
+}
+
+fn check_component<T: Component + MapEntities>(
+    world: &mut World,
+    entity: Entity,
+    entities: &EntityMap,
+) -> Result<(), SceneError> {
+    let Some(storage) = world.storage_mut::<T>() else {
+        return Ok(());
+    };
+    let Some(ticks) = storage.ticks(entity) else {
+        return Ok(());
+    };
+
+    // Mapping through a map of every entity to itself leaves the component as
+    // it was, and the component keeps the tick at which it last changed.
+    storage
+        .get_mut(entity, ticks.changed)
+        .map_or(Ok(()), |component| component.map_entities(entities))
+}
+
+/// The parent is stored as a plain entity, and restored through
+/// [`World::set_parent`] so that the [`Children`](crate::ecs::Children) of the
+/// parent are kept in sync.
+fn serialize_parent(world: &World, entity: Entity) -> Option<serde_json::Result<Value>> {
+    world.parent(entity).map(serde_json::to_value)
+}
+
+fn insert_parent(
+    world: &mut World,
+    entity: Entity,
+    name: &str,
+    value: &Value,
+    entities: &EntityMap,
+) -> Result<(), SceneError> {
+    let parent = deserialize_component::<Entity>(name, value)?;
+    world.set_parent(entity, entities.get(parent)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde_json::json;
+
+    use crate::ecs::test_util::{registered_world, Health, Target};
+    use crate::Vector2f;
+
+    #[test]
+    fn scenes_round_trip_with_remapped_entities() {
+        let mut world = registered_world();
+
+        let player = world
+            .create_entity()
+            .with(Transform::from_position(Vector2f::new(1.0, 2.0)))
+            .with(Health(10))
+            .build();
+        let weapon = world.create_entity().with(Target(player)).build();
+        world.set_parent(weapon, player).unwrap();
+
+        let json = world.save_scene().unwrap().to_json().unwrap();
+
+        let mut other = registered_world();
+        other.create_entity().build();
+
+        let map = other.load_scene(&Scene::from_json(&json).unwrap()).unwrap();
+        let loaded_player = map.get(player).unwrap();
+        let loaded_weapon = map.get(weapon).unwrap();
+
+        assert_ne!(loaded_player, player);
+        assert_eq!(
+            other.get_component::<Transform>(loaded_player),
+            Some(&Transform::from_position(Vector2f::new(1.0, 2.0)))
+        );
+        assert_eq!(
+            other.get_component::<Health>(loaded_player),
+            Some(&Health(10))
+        );
+        assert_eq!(
+            other.get_component::<Target>(loaded_weapon),
+            Some(&Target(loaded_player))
+        );
+        assert_eq!(other.parent(loaded_weapon), Some(loaded_player));
+        assert_eq!(other.children(loaded_player), &[loaded_weapon]);
+    }
+
+    #[test]
+    fn unknown_components_are_reported() {
+        let mut world = registered_world();
+        let entity = world.create_entity().build();
+
+        let scene = Scene {
+            entities: vec![SceneEntity {
+                entity,
+                components: vec![("Mana".to_string(), json!(5))].into_iter().collect(),
+            }],
+        };
+
+        match world.load_scene(&scene) {
+            Err(SceneError::UnknownComponent(name)) => assert_eq!(name, "Mana"),
+            result => panic!("expected an unknown component error, got {:?}", result),
+        }
+        assert_eq!(world.entities().len(), 1);
+    }
+
+    #[test]
+    fn failed_loads_do_not_add_entities() {
+        let mut world = registered_world();
+        let entity = world.create_entity().build();
+        let missing = world.create_entity().build();
+        world.kill_entity(missing).unwrap();
+
+        let scene = Scene {
+            entities: vec![SceneEntity {
+                entity,
+                components: vec![
+                    ("Health".to_string(), json!(5)),
+                    ("Target".to_string(), json!(missing)),
+                ]
+                .into_iter()
+                .collect(),
+            }],
+        };
+
+        match world.load_scene(&scene) {
+            Err(SceneError::UnmappedEntity(unmapped)) => assert_eq!(unmapped, missing),
+            result => panic!("expected an unmapped entity error, got {:?}", result),
+        }
+        assert_eq!(world.entities(), &[entity]);
+    }
+}
@@ -0,0 +1,900 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use rayon::{ThreadPool, ThreadPoolBuilder};
+
+use crate::ecs::{Access, Component, Resource, SystemContext, World};
+use crate::error::EcsError;
+use crate::{Context, Result};
+
+/// A stage of the game loop in which systems are run.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Stage {
+    /// Runs before [`State::update`](crate::State::update).
+    PreUpdate,
+
+    /// Runs after [`State::update`](crate::State::update).
+    Update,
+
+    /// Runs after all of the [`Stage::Update`] systems.
+    PostUpdate,
+
+    /// Runs after the screen has been cleared and before [`State::draw`](crate::State::draw).
+    Render,
+}
+
+type ExclusiveFn<G> = Box<dyn FnMut(&mut Context<G>) -> Result<()>>;
+type ParallelFn = Box<dyn FnMut(&mut SystemContext<'_>) -> Result<()> + Send>;
+
+enum SystemFn<G> {
+    Exclusive(ExclusiveFn<G>),
+    Parallel(ParallelFn),
+}
+
+/// The component and resource types that a system declares it accesses.
+#[derive(Debug, Default, Clone)]
+pub struct SystemAccess {
+    pub(crate) components: Access,
+    pub(crate) resources: Access,
+}
+
+/// A named function that is run by the engine in a given [`Stage`].
+pub struct System<G> {
+    name: &'static str,
+    before: Vec<&'static str>,
+    after: Vec<&'static str>,
+    access: SystemAccess,
+    run: SystemFn<G>,
+}
+
+impl<G> System<G> {
+    /// Creates a new [`System`] with the given name.
+    ///
+    /// The name is used to order the system relative to other systems.
+    ///
+    /// The system has exclusive access to the [`Context`], so it never runs in
+    /// parallel with other systems.
+    pub fn new<F>(name: &'static str, system: F) -> Self
+    where
+        F: FnMut(&mut Context<G>) -> Result<()> + 'static,
+    {
+        Self::with_fn(name, SystemFn::Exclusive(Box::new(system)))
+    }
+
+    /// Creates a new [`System`] with the given name that can run in parallel
+    /// with other systems.
+    ///
+    /// The system can only access the components and resources that it
+    /// declares using [`System::reads`], [`System::writes`],
+    /// [`System::reads_resource`] and [`System::writes_resource`].
+    pub fn parallel<F>(name: &'static str, system: F) -> Self
+    where
+        F: FnMut(&mut SystemContext<'_>) -> Result<()> + Send + 'static,
+    {
+        Self::with_fn(name, SystemFn::Parallel(Box::new(system)))
+    }
+
+    fn with_fn(name: &'static str, run: SystemFn<G>) -> Self {
+        Self {
+            name,
+            before: Vec::new(),
+            after: Vec::new(),
+            access: SystemAccess::default(),
+            run,
+        }
+    }
+
+    /// Returns the name of the system.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Returns whether the system has exclusive access to the [`Context`].
+    pub fn is_exclusive(&self) -> bool {
+        matches!(self.run, SystemFn::Exclusive(_))
+    }
+
+    /// Runs this system before the system with the given name.
+    pub fn before(mut self, name: &'static str) -> Self {
+        self.before.push(name);
+        self
+    }
+
+    /// Runs this system after the system with the given name.
+    pub fn after(mut self, name: &'static str) -> Self {
+        self.after.push(name);
+        self
+    }
+
+    /// Declares that the system reads components of type `T`.
+    pub fn reads<T: Component>(mut self) -> Self {
+        self.access.components.read::<T>();
+        self
+    }
+
+    /// Declares that the system reads and writes components of type `T`.
+    pub fn writes<T: Component>(mut self) -> Self {
+        self.access.components.write::<T>();
+        self
+    }
+
+    /// Declares that the system reads the resource of type `T`.
+    pub fn reads_resource<T: Resource>(mut self) -> Self {
+        self.access.resources.read::<T>();
+        self
+    }
+
+    /// Declares that the system reads and writes the resource of type `T`.
+    pub fn writes_resource<T: Resource>(mut self) -> Self {
+        self.access.resources.write::<T>();
+        self
+    }
+
+    /// Returns the reason that this system cannot run in parallel with the
+    /// other system, if there is one.
+    fn conflict_with(&self, other: &System<G>) -> Option<ConflictReason> {
+        if self.is_exclusive() || other.is_exclusive() {
+            return Some(ConflictReason::Exclusive);
+        }
+
+        if let Some(component) = self
+            .access
+            .components
+            .conflict_with(&other.access.components)
+        {
+            return Some(ConflictReason::Component(component));
+        }
+
+        self.access
+            .resources
+            .conflict_with(&other.access.resources)
+            .map(ConflictReason::Resource)
+    }
+}
+
+/// How the systems in each stage are run.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum ExecutionMode {
+    /// Runs every system on the main thread, one after another.
+    #[default]
+    Sequential,
+
+    /// Runs systems that don't conflict with each other in parallel on a
+    /// thread pool.
+    ///
+    /// Systems are grouped into batches in the order in which they would run
+    /// sequentially, and the commands they record are applied in that order,
+    /// so the results are the same as when running sequentially.
+    Parallel,
+}
+
+/// The reason that two systems cannot run in parallel.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ConflictReason {
+    /// One of the systems has exclusive access to the [`Context`].
+    Exclusive,
+
+    /// One of the systems writes a component type that the other accesses.
+    Component(&'static str),
+
+    /// One of the systems writes a resource type that the other accesses.
+    Resource(&'static str),
+}
+
+/// A system that had to wait for an earlier system in the same stage, rather
+/// than running in parallel with it, because their accesses conflict.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SystemConflict {
+    /// The stage containing the systems.
+    pub stage: Stage,
+
+    /// The name of the system that had to wait.
+    pub system: &'static str,
+
+    /// The name of the system that it waited for.
+    pub other: &'static str,
+
+    /// The reason that the systems could not run in parallel.
+    pub reason: ConflictReason,
+}
+
+impl fmt::Display for SystemConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:?}: `{}` waits for `{}` because ",
+            self.stage, self.system, self.other
+        )?;
+
+        match self.reason {
+            ConflictReason::Exclusive => write!(f, "one of them has exclusive access"),
+            ConflictReason::Component(name) => write!(f, "they conflict on component `{}`", name),
+            ConflictReason::Resource(name) => write!(f, "they conflict on resource `{}`", name),
+        }
+    }
+}
+
+struct StageSystems<G> {
+    systems: Vec<System<G>>,
+
+    /// The order in which to run the systems, or `None` if it needs to be recomputed.
+    order: Option<Vec<usize>>,
+
+    /// The systems grouped into batches that can run in parallel, in order.
+    batches: Vec<Vec<usize>>,
+
+    /// The systems that were moved into a later batch because of a conflict.
+    conflicts: Vec<SystemConflict>,
+}
+
+impl<G> Default for StageSystems<G> {
+    fn default() -> Self {
+        Self {
+            systems: Vec::new(),
+            order: None,
+            batches: Vec::new(),
+            conflicts: Vec::new(),
+        }
+    }
+}
+
+pub(crate) struct Schedule<G> {
+    stages: HashMap<Stage, StageSystems<G>>,
+    mode: ExecutionMode,
+
+    /// Whether the mode was set explicitly, in which case appending this
+    /// schedule to another carries the mode over.
+    mode_set: bool,
+
+    /// The thread pool for running systems in parallel, created when first needed.
+    pool: Option<ThreadPool>,
+}
+
+impl<G> Default for Schedule<G> {
+    fn default() -> Self {
+        Self {
+            stages: HashMap::new(),
+            mode: ExecutionMode::default(),
+            mode_set: false,
+            pool: None,
+        }
+    }
+}
+
+impl<G> Schedule<G> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_system(&mut self, stage: Stage, system: System<G>) {
+        let stage = self.stages.entry(stage).or_default();
+        stage.systems.push(system);
+        stage.order = None;
+    }
+
+    pub fn set_mode(&mut self, mode: ExecutionMode) {
+        self.mode = mode;
+        self.mode_set = true;
+    }
+
+    /// Moves all of the systems from `other` into this schedule, along with
+    /// its mode if it was set.
+    pub fn append(&mut self, other: Schedule<G>) {
+        if other.mode_set {
+            self.set_mode(other.mode);
+        }
+
+        for (stage, stage_systems) in other.stages {
+            for system in stage_systems.systems {
+                self.add_system(stage, system);
+            }
+        }
+    }
+
+    /// Computes the order of the systems in every stage.
+    pub fn initialize(&mut self) -> Result<(), EcsError> {
+        for (stage, stage_systems) in self.stages.iter_mut() {
+            if stage_systems.order.is_none() {
+                let (order, dependents) = sort_systems(*stage, &stage_systems.systems)?;
+                let (batches, conflicts) =
+                    batch_systems(*stage, &stage_systems.systems, &order, &dependents);
+
+                stage_systems.order = Some(order);
+                stage_systems.batches = batches;
+                stage_systems.conflicts = conflicts;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the systems in every stage that cannot run in parallel with an
+    /// earlier system because their accesses conflict.
+    pub fn conflicts(&mut self) -> Result<Vec<SystemConflict>, EcsError> {
+        self.initialize()?;
+
+        let mut stages = self.stages.iter().collect::<Vec<_>>();
+        stages.sort_by_key(|(stage, _)| **stage as u8);
+
+        Ok(stages
+            .into_iter()
+            .flat_map(|(_, stage_systems)| stage_systems.conflicts.iter().cloned())
+            .collect())
+    }
+
+    pub fn run_stage(&mut self, stage: Stage, ctx: &mut Context<G>) -> Result<()> {
+        self.initialize()?;
+
+        if self.mode == ExecutionMode::Parallel && self.pool.is_none() {
+            self.pool = Some(
+                ThreadPoolBuilder::new()
+                    .thread_name(|index| format!("peacock-system-{}", index))
+                    .build()?,
+            );
+        }
+
+        let stage_systems = match self.stages.get_mut(&stage) {
+            Some(stage_systems) => stage_systems,
+            None => return Ok(()),
+        };
+
+        let batches = match self.mode {
+            ExecutionMode::Sequential => stage_systems
+                .order
+                .as_ref()
+                .unwrap()
+                .iter()
+                .map(|&index| vec![index])
+                .collect(),
+            ExecutionMode::Parallel => stage_systems.batches.clone(),
+        };
+
+        for batch in batches {
+            let mut systems = batch_mut(&mut stage_systems.systems, &batch);
+
+            if let [system] = systems.as_mut_slice() {
+                if let SystemFn::Exclusive(run) = &mut system.run {
+                    run(ctx)?;
+                    continue;
+                }
+            }
+
+            run_parallel(self.pool.as_ref(), &mut systems, &mut ctx.world)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Schedule<()> {
+    /// Moves the systems that were added while a context was being built into
+    /// a schedule for the built context.
+    ///
+    /// Parallel systems don't depend on the game context, so they are kept.
+    /// Exclusive systems take a `Context<()>`, which cannot be run against the
+    /// built context, so they are rejected rather than silently dropped.
+    pub(crate) fn into_game<G>(self) -> Result<Schedule<G>, EcsError> {
+        let mut schedule = Schedule {
+            stages: HashMap::new(),
+            mode: self.mode,
+            mode_set: self.mode_set,
+            pool: self.pool,
+        };
+
+        for (stage, stage_systems) in self.stages {
+            for system in stage_systems.systems {
+                let run = match system.run {
+                    SystemFn::Parallel(run) => SystemFn::Parallel(run),
+                    SystemFn::Exclusive(_) => return Err(EcsError::SetupSystem(system.name)),
+                };
+
+                schedule.add_system(
+                    stage,
+                    System {
+                        name: system.name,
+                        before: system.before,
+                        after: system.after,
+                        access: system.access,
+                        run,
+                    },
+                );
+            }
+        }
+
+        Ok(schedule)
+    }
+}
+
+/// Returns mutable references to the systems in a batch, in the order in which
+/// they appear in the batch.
+fn batch_mut<'a, G>(systems: &'a mut [System<G>], batch: &[usize]) -> Vec<&'a mut System<G>> {
+    let mut slots = systems
+        .iter_mut()
+        .enumerate()
+        .filter_map(|(index, system)| {
+            let position = batch.iter().position(|&other| other == index)?;
+            Some((position, system))
+        })
+        .collect::<Vec<_>>();
+
+    slots.sort_by_key(|(position, _)| *position);
+    slots.into_iter().map(|(_, system)| system).collect()
+}
+
+/// Runs a batch of systems that don't conflict with each other, in parallel if
+/// a thread pool is given.
+///
+/// The commands recorded by the systems are applied to the world in the order
+/// of the batch, regardless of the order in which the systems finish.
+fn run_parallel<G>(
+    pool: Option<&ThreadPool>,
+    systems: &mut [&mut System<G>],
+    world: &mut World,
+) -> Result<()> {
+    let shared = world.share();
+
+    let mut jobs = systems
+        .iter_mut()
+        .filter_map(|system| {
+            let System {
+                name, access, run, ..
+            } = &mut **system;
+
+            match run {
+                SystemFn::Parallel(run) => {
+                    let ctx = SystemContext::new(name, &shared, access);
+                    Some((run, ctx, Ok(())))
+                }
+                SystemFn::Exclusive(_) => None,
+            }
+        })
+        .collect::<Vec<(_, _, Result<()>)>>();
+
+    match pool {
+        Some(pool) if jobs.len() > 1 => pool.scope(|scope| {
+            for (run, ctx, result) in jobs.iter_mut() {
+                scope.spawn(move |_| *result = run(ctx));
+            }
+        }),
+        _ => {
+            for (run, ctx, result) in jobs.iter_mut() {
+                *result = run(ctx);
+            }
+        }
+    }
+
+    for (_, _, result) in jobs.iter_mut() {
+        std::mem::replace(result, Ok(()))?;
+    }
+
+    for (_, ctx, _) in jobs.iter_mut() {
+        world.commands.append(&mut ctx.commands);
+    }
+
+    Ok(())
+}
+
+/// Groups the sorted systems in a stage into batches of systems that can run in
+/// parallel, returning the batches along with the conflicts that split them.
+///
+/// Each batch is a contiguous run of the sorted systems, so running the batches
+/// in order respects all of the ordering constraints.
+fn batch_systems<G>(
+    stage: Stage,
+    systems: &[System<G>],
+    order: &[usize],
+    dependents: &[Vec<usize>],
+) -> (Vec<Vec<usize>>, Vec<SystemConflict>) {
+    let mut batches: Vec<Vec<usize>> = Vec::new();
+    let mut conflicts = Vec::new();
+
+    for &index in order {
+        let system = &systems[index];
+
+        let batch = match batches.last_mut() {
+            Some(batch) => batch,
+            None => {
+                batches.push(vec![index]);
+                continue;
+            }
+        };
+
+        let conflict = batch.iter().find_map(|&other| {
+            system
+                .conflict_with(&systems[other])
+                .map(|reason| (other, reason))
+        });
+        let is_ordered = batch
+            .iter()
+            .any(|&other| dependents[other].contains(&index));
+
+        match conflict {
+            Some((other, reason)) => {
+                conflicts.push(SystemConflict {
+                    stage,
+                    system: system.name,
+                    other: systems[other].name,
+                    reason,
+                });
+                batches.push(vec![index]);
+            }
+            None if is_ordered => batches.push(vec![index]),
+            None => batch.push(index),
+        }
+    }
+
+    (batches, conflicts)
+}
+
+/// Sorts the systems in a stage so that all of their ordering constraints are
+/// satisfied.
+///
+/// Systems without any constraints between them keep the order in which they
+/// were added.
+///
+/// Returns the order along with the systems that must run after each system.
+fn sort_systems<G>(
+    stage: Stage,
+    systems: &[System<G>],
+) -> Result<(Vec<usize>, Vec<Vec<usize>>), EcsError> {
+    let mut indices = HashMap::with_capacity(systems.len());
+    for (index, system) in systems.iter().enumerate() {
+        if indices.insert(system.name, index).is_some() {
+            return Err(EcsError::DuplicateSystem(system.name));
+        }
+    }
+
+    let index_of = |system: &System<G>, other: &'static str| {
+        indices.get(other).copied().ok_or(EcsError::UnknownSystem {
+            system: system.name,
+            other,
+        })
+    };
+
+    // `dependents[a]` contains all of the systems that must run after `a`.
+    let mut dependents = vec![Vec::new(); systems.len()];
+    let mut dependency_counts = vec![0; systems.len()];
+
+    for (index, system) in systems.iter().enumerate() {
+        for &other in &system.before {
+            let other = index_of(system, other)?;
+            dependents[index].push(other);
+            dependency_counts[other] += 1;
+        }
+
+        for &other in &system.after {
+            let other = index_of(system, other)?;
+            dependents[other].push(index);
+            dependency_counts[index] += 1;
+        }
+    }
+
+    let mut order = Vec::with_capacity(systems.len());
+    let mut is_sorted = vec![false; systems.len()];
+
+    while order.len() < systems.len() {
+        let next =
+            (0..systems.len()).find(|&index| !is_sorted[index] && dependency_counts[index] == 0);
+
+        let next = match next {
+            Some(next) => next,
+            None => {
+                let cycle = find_cycle(&dependents, &is_sorted)
+                    .into_iter()
+                    .map(|index| systems[index].name)
+                    .collect::<Vec<_>>()
+                    .join(" -> ");
+
+                return Err(EcsError::SystemCycle { stage, cycle });
+            }
+        };
+
+        is_sorted[next] = true;
+        order.push(next);
+
+        for &dependent in &dependents[next] {
+            dependency_counts[dependent] -= 1;
+        }
+    }
+
+    Ok((order, dependents))
+}
+
+/// Finds a cycle among the unsorted systems, returning the systems in the cycle
+/// with the first system repeated at the end.
+fn find_cycle(dependents: &[Vec<usize>], is_sorted: &[bool]) -> Vec<usize> {
+    let start = (0..dependents.len())
+        .find(|&index| !is_sorted[index])
+        .unwrap();
+
+    // Every unsorted system has at least one unsorted dependency, so walking
+    // backwards is guaranteed to revisit a system eventually.
+    let mut path = vec![start];
+    loop {
+        let current = *path.last().unwrap();
+        let dependency = (0..dependents.len())
+            .find(|&index| !is_sorted[index] && dependents[index].contains(&current))
+            .unwrap();
+
+        if let Some(position) = path.iter().position(|&index| index == dependency) {
+            let mut cycle = path.split_off(position);
+            cycle.reverse();
+            cycle.insert(0, dependency);
+            return cycle;
+        }
+
+        path.push(dependency);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Position(f32);
+
+    impl Component for Position {}
+
+    struct Velocity(f32);
+
+    impl Component for Velocity {}
+
+    struct Label(&'static str);
+
+    impl Component for Label {}
+
+    fn system(name: &'static str) -> System<()> {
+        System::new(name, |_| Ok(()))
+    }
+
+    fn parallel_system(name: &'static str) -> System<()> {
+        System::parallel(name, |_| Ok(()))
+    }
+
+    fn batches_of(schedule: &Schedule<()>, stage: Stage) -> Vec<Vec<&'static str>> {
+        let stage_systems = &schedule.stages[&stage];
+
+        stage_systems
+            .batches
+            .iter()
+            .map(|batch| {
+                batch
+                    .iter()
+                    .map(|&index| stage_systems.systems[index].name)
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn order_of(schedule: &Schedule<()>, stage: Stage) -> Vec<&'static str> {
+        let stage_systems = &schedule.stages[&stage];
+
+        stage_systems
+            .order
+            .as_ref()
+            .unwrap()
+            .iter()
+            .map(|&index| stage_systems.systems[index].name)
+            .collect()
+    }
+
+    #[test]
+    fn systems_run_in_the_order_they_were_added() {
+        let mut schedule = Schedule::new();
+        schedule.add_system(Stage::Update, system("a"));
+        schedule.add_system(Stage::Update, system("b"));
+        schedule.add_system(Stage::Update, system("c"));
+
+        assert_eq!(schedule.initialize(), Ok(()));
+        assert_eq!(order_of(&schedule, Stage::Update), vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn systems_respect_ordering_constraints() {
+        let mut schedule = Schedule::new();
+        schedule.add_system(Stage::Update, system("render_prep").after("physics"));
+        schedule.add_system(Stage::Update, system("physics"));
+        schedule.add_system(Stage::Update, system("input").before("physics"));
+
+        assert_eq!(schedule.initialize(), Ok(()));
+        assert_eq!(
+            order_of(&schedule, Stage::Update),
+            vec!["input", "physics", "render_prep"]
+        );
+    }
+
+    #[test]
+    fn cyclic_constraints_are_reported() {
+        let mut schedule = Schedule::new();
+        schedule.add_system(Stage::Update, system("a").after("c"));
+        schedule.add_system(Stage::Update, system("b").after("a"));
+        schedule.add_system(Stage::Update, system("c").after("b"));
+        schedule.add_system(Stage::Update, system("d"));
+
+        assert_eq!(
+            schedule.initialize(),
+            Err(EcsError::SystemCycle {
+                stage: Stage::Update,
+                cycle: "a -> b -> c -> a".into()
+            })
+        );
+    }
+
+    #[test]
+    fn unknown_systems_are_reported() {
+        let mut schedule = Schedule::new();
+        schedule.add_system(Stage::Update, system("a").after("missing"));
+
+        assert_eq!(
+            schedule.initialize(),
+            Err(EcsError::UnknownSystem {
+                system: "a",
+                other: "missing"
+            })
+        );
+    }
+
+    #[test]
+    fn systems_without_conflicts_are_batched_together() {
+        let mut schedule = Schedule::new();
+        schedule.add_system(Stage::Update, parallel_system("move").writes::<Position>());
+        schedule.add_system(Stage::Update, parallel_system("drag").writes::<Velocity>());
+        schedule.add_system(
+            Stage::Update,
+            parallel_system("render_prep").reads::<Position>(),
+        );
+        schedule.add_system(Stage::Update, parallel_system("debug").reads::<Velocity>());
+        schedule.add_system(Stage::Update, system("exclusive"));
+
+        assert_eq!(
+            schedule.conflicts(),
+            Ok(vec![
+                SystemConflict {
+                    stage: Stage::Update,
+                    system: "render_prep",
+                    other: "move",
+                    reason: ConflictReason::Component(std::any::type_name::<Position>()),
+                },
+                SystemConflict {
+                    stage: Stage::Update,
+                    system: "exclusive",
+                    other: "render_prep",
+                    reason: ConflictReason::Exclusive,
+                },
+            ])
+        );
+        assert_eq!(
+            batches_of(&schedule, Stage::Update),
+            vec![
+                vec!["move", "drag"],
+                vec!["render_prep", "debug"],
+                vec!["exclusive"]
+            ]
+        );
+    }
+
+    #[test]
+    fn ordering_constraints_split_batches() {
+        let mut schedule = Schedule::new();
+        schedule.add_system(Stage::Update, parallel_system("a"));
+        schedule.add_system(Stage::Update, parallel_system("b").after("a"));
+
+        assert_eq!(schedule.conflicts(), Ok(Vec::new()));
+        assert_eq!(
+            batches_of(&schedule, Stage::Update),
+            vec![vec!["a"], vec!["b"]]
+        );
+    }
+
+    #[test]
+    fn parallel_systems_are_deterministic() {
+        let pool = ThreadPoolBuilder::new().num_threads(4).build().unwrap();
+
+        for _ in 0..10 {
+            let mut world = World::new();
+            for value in 0..100 {
+                world
+                    .create_entity()
+                    .with(Position(value as f32))
+                    .with(Velocity(1.0))
+                    .build();
+            }
+
+            let mut systems = ["first", "second", "third"]
+                .iter()
+                .map(|&name| {
+                    System::<()>::parallel(name, move |ctx| {
+                        ctx.commands().spawn().with(Label(name)).build();
+                        Ok(())
+                    })
+                })
+                .collect::<Vec<_>>();
+            systems.push(
+                System::parallel("move", |ctx| {
+                    for (_, (position, velocity)) in ctx.query::<(&mut Position, &Velocity)>()? {
+                        position.0 += velocity.0;
+                    }
+                    Ok(())
+                })
+                .writes::<Position>()
+                .reads::<Velocity>(),
+            );
+
+            let mut batch = systems.iter_mut().collect::<Vec<_>>();
+            run_parallel(Some(&pool), &mut batch, &mut world).unwrap();
+            world.apply_commands().unwrap();
+
+            let labels = world
+                .query::<&Label>()
+                .unwrap()
+                .map(|(_, label)| label.0)
+                .collect::<Vec<_>>();
+            assert_eq!(labels, vec!["first", "second", "third"]);
+
+            let total = world
+                .query::<&Position>()
+                .unwrap()
+                .map(|(_, position)| position.0)
+                .sum::<f32>();
+            assert_eq!(total, 5050.0);
+        }
+    }
+
+    #[test]
+    fn parallel_systems_cannot_access_undeclared_components() {
+        let mut world = World::new();
+
+        let mut system = System::<()>::parallel("sneaky", |ctx| {
+            ctx.query::<&mut Position>()?;
+            Ok(())
+        })
+        .reads::<Position>();
+
+        let error = run_parallel(None, &mut [&mut system], &mut world).unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<EcsError>(),
+            Some(&EcsError::UndeclaredAccess {
+                system: "sneaky",
+                type_name: std::any::type_name::<Position>(),
+            })
+        );
+    }
+
+    #[test]
+    fn append_carries_over_the_mode_if_it_was_set() {
+        let mut schedule = Schedule::<()>::new();
+        schedule.set_mode(ExecutionMode::Parallel);
+
+        schedule.append(Schedule::new());
+        assert_eq!(schedule.mode, ExecutionMode::Parallel);
+
+        let mut added = Schedule::new();
+        added.set_mode(ExecutionMode::Sequential);
+        added.add_system(Stage::Update, system("a"));
+
+        schedule.append(added);
+        assert_eq!(schedule.mode, ExecutionMode::Sequential);
+        assert_eq!(schedule.stages[&Stage::Update].systems.len(), 1);
+    }
+
+    #[test]
+    fn into_game_keeps_parallel_systems_and_rejects_exclusive_ones() {
+        let mut schedule = Schedule::new();
+        schedule.set_mode(ExecutionMode::Parallel);
+        schedule.add_system(Stage::Update, parallel_system("a"));
+        schedule.add_system(Stage::Update, parallel_system("b").after("a"));
+
+        let schedule = schedule.into_game::<u32>().unwrap();
+        assert_eq!(schedule.mode, ExecutionMode::Parallel);
+        assert_eq!(schedule.stages[&Stage::Update].systems.len(), 2);
+
+        let mut schedule = Schedule::new();
+        schedule.add_system(Stage::Update, parallel_system("a"));
+        schedule.add_system(Stage::Update, system("b"));
+
+        assert_eq!(
+            schedule.into_game::<u32>().err(),
+            Some(EcsError::SetupSystem("b"))
+        );
+    }
+}
@@ -0,0 +1,398 @@
+use std::any::{type_name, TypeId};
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+
+use crate::ecs::{
+    Allocator, AnyStorage, Bounds, Children, Component, Entity, GlobalTransform, Name, NameIndex,
+    Parent, SparseSet, Transform, World,
+};
+
+type CloneFn = fn(&dyn AnyStorage) -> Box<dyn AnyStorage>;
+
+type EqFn = fn(&dyn AnyStorage, &dyn AnyStorage) -> bool;
+
+type HashFn = fn(&dyn AnyStorage, &mut ChecksumHasher);
+
+#[derive(Clone, Copy)]
+struct SnapshotFns {
+    /// The name of the component type, which orders the storages in a checksum.
+    name: &'static str,
+    clone: CloneFn,
+    eq: EqFn,
+    hash: HashFn,
+}
+
+fn clone_storage<T: Component + Clone>(storage: &dyn AnyStorage) -> Box<dyn AnyStorage> {
+    Box::new(storage_of::<T>(storage).clone())
+}
+
+fn storages_eq<T: Component + PartialEq>(left: &dyn AnyStorage, right: &dyn AnyStorage) -> bool {
+    storage_of::<T>(left).same_components(storage_of::<T>(right))
+}
+
+fn hash_storage<T: Component + Hash>(storage: &dyn AnyStorage, hasher: &mut ChecksumHasher) {
+    for (entity, component) in storage_of::<T>(storage).iter() {
+        entity.hash(hasher);
+        component.hash(hasher);
+    }
+}
+
+fn storage_of<T: Component>(storage: &dyn AnyStorage) -> &SparseSet<T> {
+    storage.as_any().downcast_ref::<SparseSet<T>>().unwrap()
+}
+
+/// The component types that are included in [`Snapshot`]s.
+#[derive(Clone)]
+pub(crate) struct SnapshotRegistry {
+    types: HashMap<TypeId, SnapshotFns>,
+}
+
+impl SnapshotRegistry {
+    /// Returns the registry, with the built-in components registered by default.
+    pub(crate) fn new() -> Self {
+        let mut registry = Self {
+            types: HashMap::new(),
+        };
+        registry.register::<Name>();
+        registry.register::<Parent>();
+        registry.register::<Children>();
+        registry.register::<Transform>();
+        registry.register::<GlobalTransform>();
+        registry.register::<Bounds>();
+        registry
+    }
+
+    fn register<T: Component + Clone + PartialEq + Hash>(&mut self) {
+        self.types.insert(
+            TypeId::of::<T>(),
+            SnapshotFns {
+                name: type_name::<T>(),
+                clone: clone_storage::<T>,
+                eq: storages_eq::<T>,
+                hash: hash_storage::<T>,
+            },
+        );
+    }
+}
+
+/// A 64-bit FNV-1a hasher.
+///
+/// Unlike the standard library's hasher, its output only depends on the
+/// values that are hashed, so checksums can be compared between processes.
+pub(crate) struct ChecksumHasher(u64);
+
+impl ChecksumHasher {
+    fn new() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Hasher for ChecksumHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 ^= u64::from(byte);
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    fn write_usize(&mut self, value: usize) {
+        // Hash lengths the same way on 32-bit and 64-bit platforms.
+        self.write_u64(value as u64);
+    }
+}
+
+/// Hashes a float by its bits, treating `-0.0` as `0.0` so that floats that
+/// compare equal hash the same.
+///
+/// Used by the built-in components that contain floats to implement `Hash`,
+/// so that they can be included in [`Snapshot::checksum`].
+pub(crate) fn hash_f32<H: Hasher>(value: f32, state: &mut H) {
+    let value = if value == 0.0 { 0.0 } else { value };
+    value.to_bits().hash(state);
+}
+
+/// A copy of the simulation state of a [`World`], which can be restored later.
+///
+/// A snapshot holds the entities, the generations of their IDs, and the
+/// components whose types were registered with [`World::register_snapshot`].
+/// Cloning a snapshot is cheap, since the copied components are shared.
+///
+/// Each snapshot has a [checksum](Snapshot::checksum) of its entities and
+/// components, which is cheap to compare and can be sent to other processes to
+/// detect when two simulations have diverged. Two snapshots compare equal with
+/// `==` when they hold the same entities and equal components, which compares
+/// every copied component when their checksums match.
+#[derive(Clone)]
+pub struct Snapshot {
+    entities: Vec<Entity>,
+    entity_indices: Vec<Option<u32>>,
+    allocator: Allocator,
+    storages: Vec<SnapshotStorage>,
+    checksum: u64,
+}
+
+#[derive(Clone)]
+struct SnapshotStorage {
+    component_type: TypeId,
+    fns: SnapshotFns,
+
+    /// The copied storage, or `None` if the world had no storage for the type.
+    storage: Option<Arc<dyn AnyStorage>>,
+}
+
+impl Snapshot {
+    /// Returns the entities in the snapshot.
+    pub fn entities(&self) -> &[Entity] {
+        &self.entities
+    }
+
+    /// Returns a checksum of the entities, the generations of their IDs and
+    /// the copied components, which was computed when the snapshot was taken.
+    ///
+    /// Snapshots that compare equal have the same checksum. The checksum does
+    /// not depend on the process that took the snapshot, so peers running the
+    /// same build can exchange checksums instead of whole snapshots.
+    pub fn checksum(&self) -> u64 {
+        self.checksum
+    }
+}
+
+impl PartialEq for Snapshot {
+    fn eq(&self, other: &Self) -> bool {
+        self.checksum == other.checksum
+            && self.entities == other.entities
+            && self.allocator == other.allocator
+            && self.storages.len() == other.storages.len()
+            && self.storages.iter().all(|storage| {
+                let other = other
+                    .storages
+                    .iter()
+                    .find(|other| other.component_type == storage.component_type);
+
+                match (&storage.storage, other.map(|other| &other.storage)) {
+                    (Some(left), Some(Some(right))) => {
+                        Arc::ptr_eq(left, right) || (storage.fns.eq)(left.as_ref(), right.as_ref())
+                    }
+                    (None, Some(None)) => true,
+                    _ => false,
+                }
+            })
+    }
+}
+
+impl World {
+    /// Includes components of type `T` in [`Snapshot`]s of the world.
+    ///
+    /// [`Name`], [`Parent`], [`Children`], [`Transform`], [`GlobalTransform`]
+    /// and [`Bounds`] are registered by default.
+    ///
+    /// The components are hashed into the [`Snapshot::checksum`], so `Hash`
+    /// must agree with `PartialEq`.
+    pub fn register_snapshot<T: Component + Clone + PartialEq + Hash>(&mut self) {
+        self.snapshot_registry.register::<T>();
+    }
+
+    /// Returns a [`Snapshot`] of the entities in the world and their
+    /// registered components.
+    pub fn snapshot(&self) -> Snapshot {
+        let storages = self
+            .snapshot_registry
+            .types
+            .iter()
+            .map(|(&component_type, &fns)| SnapshotStorage {
+                component_type,
+                fns,
+                storage: self
+                    .components
+                    .get(&component_type)
+                    .map(|storage| Arc::from((fns.clone)(storage.as_ref()))),
+            })
+            .collect::<Vec<_>>();
+
+        let mut hasher = ChecksumHasher::new();
+        self.entities.hash(&mut hasher);
+        self.allocator.hash(&mut hasher);
+
+        let mut ordered = storages.iter().collect::<Vec<_>>();
+        ordered.sort_by_key(|storage| storage.fns.name);
+        for storage in ordered {
+            storage.fns.name.hash(&mut hasher);
+            match &storage.storage {
+                Some(copied) => {
+                    true.hash(&mut hasher);
+                    (storage.fns.hash)(copied.as_ref(), &mut hasher);
+                }
+                None => false.hash(&mut hasher),
+            }
+        }
+
+        Snapshot {
+            entities: self.entities.clone(),
+            entity_indices: self.entity_indices.clone(),
+            allocator: self.allocator.clone(),
+            storages,
+            checksum: hasher.finish(),
+        }
+    }
+
+    /// Restores the world to the state in the given [`Snapshot`].
+    ///
+    /// Components whose types were not in the snapshot are kept on the
+    /// entities that are still alive afterwards, and are removed from the
+    /// rest. No hooks are run, and resources are left untouched. The spatial
+    /// index is rebuilt.
+    pub fn restore(&mut self, snapshot: &Snapshot) {
+        self.entities.clone_from(&snapshot.entities);
+        self.entity_indices.clone_from(&snapshot.entity_indices);
+        self.allocator.clone_from(&snapshot.allocator);
+
+        for restored in &snapshot.storages {
+            match &restored.storage {
+                Some(storage) => {
+                    self.components.insert(
+                        restored.component_type,
+                        (restored.fns.clone)(storage.as_ref()),
+                    );
+                }
+                None => {
+                    self.components.remove(&restored.component_type);
+                }
+            }
+        }
+
+        let allocator = &self.allocator;
+        for (component_type, storage) in self.components.iter_mut() {
+            if snapshot
+                .storages
+                .iter()
+                .any(|restored| restored.component_type == *component_type)
+            {
+                continue;
+            }
+
+            let dead = storage
+                .entities()
+                .iter()
+                .copied()
+                .filter(|&entity| !allocator.is_alive(entity))
+                .collect::<Vec<_>>();
+            for entity in dead {
+                storage.remove(entity, &mut |_| {});
+            }
+        }
+
+        let mut names = NameIndex::new();
+        if let Some(storage) = self.storage::<Name>() {
+            for (entity, name) in storage.iter() {
+                names.insert(entity, name);
+            }
+        }
+        self.names = names;
+
+        self.rebuild_spatial_index(self.spatial_index.cell_size());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::Vector2f;
+
+    #[derive(Debug, Clone, PartialEq, Hash)]
+    struct Health(u32);
+
+    impl Component for Health {}
+
+    #[derive(Debug, PartialEq)]
+    struct Sprite(u32);
+
+    impl Component for Sprite {}
+
+    fn world() -> World {
+        let mut world = World::new();
+        world.register_snapshot::<Health>();
+        world
+    }
+
+    #[test]
+    fn restore_rolls_back_entities_and_components() {
+        let mut world = world();
+
+        let fighter = world
+            .create_entity()
+            .with(Name::new("fighter"))
+            .with(Health(100))
+            .with(Sprite(1))
+            .build();
+        let snapshot = world.snapshot();
+
+        world.get_component_mut::<Health>(fighter).unwrap().0 = 50;
+        world.kill_entity(fighter).unwrap();
+        let projectile = world
+            .create_entity()
+            .with(Transform::from_position(Vector2f::UNIT_X))
+            .with(Sprite(2))
+            .build();
+
+        world.restore(&snapshot);
+
+        assert!(world.is_alive(fighter));
+        assert!(!world.is_alive(projectile));
+        assert_eq!(world.entities(), &[fighter]);
+        assert_eq!(world.get_component(fighter), Some(&Health(100)));
+        assert_eq!(world.find_by_name("fighter"), Some(fighter));
+        assert!(world.storage::<Transform>().is_none());
+        assert!(world.storage::<Sprite>().unwrap().is_empty());
+    }
+
+    #[test]
+    fn snapshots_can_be_compared() {
+        let mut world = world();
+        let fighter = world.create_entity().with(Health(100)).build();
+
+        let before = world.snapshot();
+        assert!(before == before.clone());
+        assert!(before == world.snapshot());
+        assert_eq!(before.checksum(), world.snapshot().checksum());
+
+        world.get_component_mut::<Health>(fighter).unwrap().0 = 90;
+        assert!(before != world.snapshot());
+        assert_ne!(before.checksum(), world.snapshot().checksum());
+
+        world.restore(&before);
+        assert!(before == world.snapshot());
+        assert_eq!(before.checksum(), world.snapshot().checksum());
+    }
+
+    #[test]
+    fn checksums_match_between_identical_worlds() {
+        let simulate = || {
+            let mut world = world();
+            let fighter = world
+                .create_entity()
+                .with(Health(100))
+                .with(Transform::from_position(Vector2f::new(-0.0, 1.0)))
+                .build();
+            world.kill_entity(fighter).unwrap();
+            world
+                .create_entity()
+                .with(Health(90))
+                .with(Transform::from_position(Vector2f::new(0.0, 1.0)))
+                .build();
+            world.snapshot()
+        };
+
+        // The component types are hashed in the same order, even though each
+        // world keeps them in a differently ordered map.
+        let snapshots = (0..8).map(|_| simulate()).collect::<Vec<_>>();
+        assert!(snapshots
+            .iter()
+            .all(|snapshot| snapshot.checksum() == snapshots[0].checksum()));
+    }
+}
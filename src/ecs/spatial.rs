@@ -0,0 +1,525 @@
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+
+use crate::ecs::{hash_f32, Changed, Component, Entity, GlobalTransform, World};
+use crate::error::EcsError;
+use crate::graphics::Rectangle;
+use crate::Vector2f;
+
+/// The default size of the cells in the spatial index, in pixels.
+pub const DEFAULT_CELL_SIZE: f32 = 64.0;
+
+/// The most cells that an entity is added to. Entities whose bounds cover more
+/// cells than this are kept apart and checked by every query instead.
+const MAX_CELLS_PER_ENTRY: u64 = 1024;
+
+/// The area that an entity occupies, relative to the position of its
+/// [`GlobalTransform`].
+///
+/// Entities with both [`Bounds`] and a [`GlobalTransform`] are tracked by the
+/// world's spatial index, and can be found with [`World::entities_in_rect`],
+/// [`World::entities_in_radius`] and [`World::nearest_entities`].
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Bounds(pub Rectangle<f32>);
+
+impl Bounds {
+    /// Returns [`Bounds`] of the given size, centered on the position of the entity.
+    pub fn centered(width: f32, height: f32) -> Self {
+        Self(Rectangle::new(-width / 2.0, -height / 2.0, width, height))
+    }
+}
+
+impl Hash for Bounds {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        let Bounds(rect) = self;
+        for value in [rect.x, rect.y, rect.width, rect.height] {
+            hash_f32(value, state);
+        }
+    }
+}
+
+impl Component for Bounds {}
+
+type Cell = (i32, i32);
+
+struct SpatialEntry {
+    rect: Rectangle<f32>,
+    min: Cell,
+    max: Cell,
+}
+
+/// A spatial hash of the entities with [`Bounds`], bucketed into square cells.
+pub(crate) struct SpatialIndex {
+    cell_size: f32,
+    cells: HashMap<Cell, Vec<Entity>>,
+    entries: HashMap<Entity, SpatialEntry>,
+
+    /// The entities that cover more than [`MAX_CELLS_PER_ENTRY`] cells, which
+    /// are not added to any cell.
+    oversized: HashSet<Entity>,
+}
+
+impl SpatialIndex {
+    pub(crate) fn new(cell_size: f32) -> Self {
+        debug_assert!(cell_size.is_finite() && cell_size > 0.0);
+
+        Self {
+            cell_size,
+            cells: HashMap::new(),
+            entries: HashMap::new(),
+            oversized: HashSet::new(),
+        }
+    }
+
+    pub(crate) fn cell_size(&self) -> f32 {
+        self.cell_size
+    }
+
+    fn cell_of(&self, point: Vector2f) -> Cell {
+        (
+            (point.x / self.cell_size).floor() as i32,
+            (point.y / self.cell_size).floor() as i32,
+        )
+    }
+
+    fn insert(&mut self, entity: Entity, rect: Rectangle<f32>) {
+        self.remove(entity);
+
+        let min = self.cell_of(Vector2f::new(rect.left(), rect.top()));
+        let max = self.cell_of(Vector2f::new(rect.right(), rect.bottom()));
+        if cell_count(min, max) > MAX_CELLS_PER_ENTRY {
+            self.oversized.insert(entity);
+        } else {
+            for cell in cells_between(min, max) {
+                self.cells.entry(cell).or_default().push(entity);
+            }
+        }
+
+        self.entries.insert(entity, SpatialEntry { rect, min, max });
+    }
+
+    pub(crate) fn remove(&mut self, entity: Entity) {
+        let Some(entry) = self.entries.remove(&entity) else {
+            return;
+        };
+
+        if self.oversized.remove(&entity) {
+            return;
+        }
+
+        for cell in cells_between(entry.min, entry.max) {
+            if let Some(entities) = self.cells.get_mut(&cell) {
+                entities.retain(|e| *e != entity);
+                if entities.is_empty() {
+                    self.cells.remove(&cell);
+                }
+            }
+        }
+    }
+
+    /// Returns the entities in the given cells, along with the oversized
+    /// entities, that `keep` returns `true` for.
+    fn collect(
+        &self,
+        min: Cell,
+        max: Cell,
+        mut keep: impl FnMut(&Rectangle<f32>) -> bool,
+    ) -> Vec<Entity> {
+        let mut seen = HashSet::new();
+        let mut found = Vec::new();
+        let mut visit = |entity: Entity| {
+            if seen.insert(entity) && keep(&self.entries[&entity].rect) {
+                found.push(entity);
+            }
+        };
+
+        self.oversized.iter().copied().for_each(&mut visit);
+
+        // A large area can cover far more cells than are occupied, so only
+        // look at the occupied cells in that case.
+        if cell_count(min, max) > self.cells.len() as u64 {
+            for (cell, entities) in &self.cells {
+                if (min.0..=max.0).contains(&cell.0) && (min.1..=max.1).contains(&cell.1) {
+                    entities.iter().copied().for_each(&mut visit);
+                }
+            }
+        } else {
+            for cell in cells_between(min, max) {
+                if let Some(entities) = self.cells.get(&cell) {
+                    entities.iter().copied().for_each(&mut visit);
+                }
+            }
+        }
+
+        found
+    }
+
+    fn in_rect(&self, rect: &Rectangle<f32>) -> Vec<Entity> {
+        let min = self.cell_of(Vector2f::new(rect.left(), rect.top()));
+        let max = self.cell_of(Vector2f::new(rect.right(), rect.bottom()));
+
+        let mut found = self.collect(min, max, |bounds| overlaps(bounds, rect));
+        found.sort_by_key(|entity| entity.to_bits());
+        found
+    }
+
+    fn in_radius(&self, center: Vector2f, radius: f32) -> Vec<Entity> {
+        if radius.is_nan() || radius < 0.0 {
+            return Vec::new();
+        }
+
+        let min = self.cell_of(Vector2f::new(center.x - radius, center.y - radius));
+        let max = self.cell_of(Vector2f::new(center.x + radius, center.y + radius));
+
+        let mut found = self.collect(min, max, |bounds| {
+            distance_squared(bounds, center) <= radius * radius
+        });
+        found.sort_by_key(|entity| entity.to_bits());
+        found
+    }
+
+    /// Visits the occupied cells outwards from the cell containing the point,
+    /// one ring of cells at a time, until no unvisited cell can hold a closer
+    /// entity or every entity has been seen.
+    fn nearest(&self, point: Vector2f, count: usize) -> Vec<Entity> {
+        if count == 0 || self.entries.is_empty() {
+            return Vec::new();
+        }
+
+        let origin = self.cell_of(point);
+        let mut cells = self
+            .cells
+            .keys()
+            .map(|&cell| (ring_of(origin, cell), cell))
+            .collect::<Vec<_>>();
+        cells.sort_unstable();
+
+        let distance = |entity: Entity| distance_squared(&self.entries[&entity].rect, point);
+
+        // The oversized entities are not in any cell, so they are always candidates.
+        let mut seen = self.oversized.clone();
+        let mut candidates = self
+            .oversized
+            .iter()
+            .map(|&entity| (distance(entity), entity))
+            .collect::<Vec<_>>();
+
+        let mut cells = cells.into_iter().peekable();
+        while let Some(&(ring, _)) = cells.peek() {
+            while let Some((_, cell)) = cells.next_if(|&(cell_ring, _)| cell_ring == ring) {
+                for &entity in &self.cells[&cell] {
+                    if seen.insert(entity) {
+                        candidates.push((distance(entity), entity));
+                    }
+                }
+            }
+
+            if seen.len() == self.entries.len() {
+                break;
+            }
+
+            sort_by_distance(&mut candidates);
+
+            // Anything in a further ring is at least this far from the point.
+            let reach = ring as f32 * self.cell_size;
+            if candidates.len() >= count && candidates[count - 1].0 <= reach * reach {
+                break;
+            }
+        }
+
+        sort_by_distance(&mut candidates);
+        candidates.truncate(count);
+        candidates.into_iter().map(|(_, entity)| entity).collect()
+    }
+}
+
+fn cells_between(min: Cell, max: Cell) -> impl Iterator<Item = Cell> {
+    (min.1..=max.1).flat_map(move |y| (min.0..=max.0).map(move |x| (x, y)))
+}
+
+/// Returns the number of cells between the two cells, inclusive.
+fn cell_count(min: Cell, max: Cell) -> u64 {
+    let columns = (i64::from(max.0) - i64::from(min.0) + 1).max(0) as u64;
+    let rows = (i64::from(max.1) - i64::from(min.1) + 1).max(0) as u64;
+    columns.saturating_mul(rows)
+}
+
+/// Returns the distance of the cell from the origin, in square rings of cells.
+fn ring_of(origin: Cell, cell: Cell) -> i64 {
+    let dx = (i64::from(cell.0) - i64::from(origin.0)).abs();
+    let dy = (i64::from(cell.1) - i64::from(origin.1)).abs();
+    dx.max(dy)
+}
+
+fn sort_by_distance(candidates: &mut [(f32, Entity)]) {
+    candidates.sort_by(|(a, a_entity), (b, b_entity)| {
+        a.total_cmp(b)
+            .then_with(|| a_entity.to_bits().cmp(&b_entity.to_bits()))
+    });
+}
+
+fn overlaps(a: &Rectangle<f32>, b: &Rectangle<f32>) -> bool {
+    a.left() <= b.right() && b.left() <= a.right() && a.top() <= b.bottom() && b.top() <= a.bottom()
+}
+
+/// Returns the squared distance from the point to the closest point in the rectangle.
+fn distance_squared(rect: &Rectangle<f32>, point: Vector2f) -> f32 {
+    let dx = (rect.left() - point.x).max(point.x - rect.right()).max(0.0);
+    let dy = (rect.top() - point.y).max(point.y - rect.bottom()).max(0.0);
+    dx * dx + dy * dy
+}
+
+impl World {
+    /// Brings the spatial index up to date with the entities whose [`Bounds`]
+    /// or [`GlobalTransform`] were added, changed or removed during the
+    /// current tick.
+    ///
+    /// This is run by the engine once per update, after transforms are propagated.
+    pub fn update_spatial_index(&mut self) {
+        let mut stale = self
+            .removed::<Bounds>()
+            .chain(self.removed::<GlobalTransform>())
+            .collect::<Vec<_>>();
+
+        stale.extend(
+            self.query_filtered::<&GlobalTransform, Changed<GlobalTransform>>()
+                .into_iter()
+                .flatten()
+                .map(|(entity, _)| entity),
+        );
+        stale.extend(
+            self.query_filtered::<&Bounds, Changed<Bounds>>()
+                .into_iter()
+                .flatten()
+                .map(|(entity, _)| entity),
+        );
+
+        for entity in stale {
+            match self.spatial_rect(entity) {
+                Some(rect) => self.spatial_index.insert(entity, rect),
+                None => self.spatial_index.remove(entity),
+            }
+        }
+    }
+
+    /// Rebuilds the spatial index from scratch with the given cell size.
+    pub(crate) fn rebuild_spatial_index(&mut self, cell_size: f32) {
+        self.spatial_index = SpatialIndex::new(cell_size);
+
+        for index in 0..self.entities.len() {
+            let entity = self.entities[index];
+            if let Some(rect) = self.spatial_rect(entity) {
+                self.spatial_index.insert(entity, rect);
+            }
+        }
+    }
+
+    /// Returns the area that the entity occupies in the world, if it is tracked
+    /// by the spatial index.
+    fn spatial_rect(&self, entity: Entity) -> Option<Rectangle<f32>> {
+        let transform = self.get_component::<GlobalTransform>(entity)?;
+        let Bounds(rect) = *self.get_component::<Bounds>(entity)?;
+
+        Some(Rectangle::new(
+            transform.position.x + rect.x,
+            transform.position.y + rect.y,
+            rect.width,
+            rect.height,
+        ))
+    }
+
+    /// Sets the size of the cells in the spatial index, and rebuilds it.
+    ///
+    /// Cells around the size of a typical entity work best. Returns an error
+    /// if the size is not a positive, finite number.
+    pub fn set_spatial_cell_size(&mut self, cell_size: f32) -> Result<(), EcsError> {
+        if !cell_size.is_finite() || cell_size <= 0.0 {
+            return Err(EcsError::InvalidCellSize(cell_size));
+        }
+
+        self.rebuild_spatial_index(cell_size);
+        Ok(())
+    }
+
+    /// Returns the entities whose [`Bounds`] overlap the given rectangle.
+    pub fn entities_in_rect(&self, rect: &Rectangle<f32>) -> Vec<Entity> {
+        self.spatial_index.in_rect(rect)
+    }
+
+    /// Returns the entities whose [`Bounds`] are within the given distance of a point.
+    pub fn entities_in_radius(&self, center: Vector2f, radius: f32) -> Vec<Entity> {
+        self.spatial_index.in_radius(center, radius)
+    }
+
+    /// Returns up to `count` entities whose [`Bounds`] are closest to the
+    /// given point, nearest first.
+    pub fn nearest_entities(&self, point: Vector2f, count: usize) -> Vec<Entity> {
+        self.spatial_index.nearest(point, count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::ecs::Transform;
+
+    fn spawn(world: &mut World, x: f32, y: f32) -> Entity {
+        world
+            .create_entity()
+            .with(Transform::from_position(Vector2f::new(x, y)))
+            .with(Bounds::centered(10.0, 10.0))
+            .build()
+    }
+
+    fn update(world: &mut World) {
+        world.propagate_transforms();
+        world.update_spatial_index();
+        world.clear_trackers();
+    }
+
+    #[test]
+    fn rect_and_radius_queries_find_overlapping_entities() {
+        let mut world = World::new();
+
+        let near = spawn(&mut world, 0.0, 0.0);
+        let edge = spawn(&mut world, 100.0, 0.0);
+        let far = spawn(&mut world, 500.0, -500.0);
+        update(&mut world);
+
+        assert_eq!(
+            world.entities_in_rect(&Rectangle::new(-10.0, -10.0, 106.0, 20.0)),
+            vec![near, edge]
+        );
+        assert_eq!(
+            world.entities_in_radius(Vector2f::new(0.0, 0.0), 95.0),
+            vec![near, edge]
+        );
+        assert_eq!(
+            world.entities_in_radius(Vector2f::new(0.0, 0.0), 94.0),
+            vec![near]
+        );
+        assert_eq!(
+            world.entities_in_radius(Vector2f::new(500.0, -500.0), 1.0),
+            vec![far]
+        );
+    }
+
+    #[test]
+    fn index_follows_moved_and_removed_entities() {
+        let mut world = World::new();
+
+        let mover = spawn(&mut world, 0.0, 0.0);
+        let doomed = spawn(&mut world, 20.0, 0.0);
+        update(&mut world);
+
+        world
+            .get_component_mut::<Transform>(mover)
+            .unwrap()
+            .position = Vector2f::new(1000.0, 1000.0);
+        world.kill_entity(doomed).unwrap();
+        update(&mut world);
+
+        assert!(world.entities_in_radius(Vector2f::ZERO, 50.0).is_empty());
+        assert_eq!(
+            world.entities_in_radius(Vector2f::new(1000.0, 1000.0), 1.0),
+            vec![mover]
+        );
+
+        world.remove_component::<Bounds>(mover);
+        update(&mut world);
+        assert!(world
+            .entities_in_radius(Vector2f::new(1000.0, 1000.0), 1.0)
+            .is_empty());
+    }
+
+    #[test]
+    fn killed_entities_are_not_found_before_the_next_update() {
+        let mut world = World::new();
+
+        let survivor = spawn(&mut world, 0.0, 0.0);
+        let doomed = spawn(&mut world, 5.0, 0.0);
+        update(&mut world);
+
+        world.kill_entity(doomed).unwrap();
+
+        assert_eq!(
+            world.entities_in_radius(Vector2f::ZERO, 50.0),
+            vec![survivor]
+        );
+        assert_eq!(
+            world.nearest_entities(Vector2f::new(5.0, 0.0), 2),
+            vec![survivor]
+        );
+    }
+
+    #[test]
+    fn nearest_entities_are_ordered_by_distance() {
+        let mut world = World::new();
+
+        let a = spawn(&mut world, 10.0, 0.0);
+        let b = spawn(&mut world, -300.0, 0.0);
+        let c = spawn(&mut world, 0.0, 150.0);
+        let d = spawn(&mut world, 1000.0, 1000.0);
+        update(&mut world);
+
+        assert_eq!(world.nearest_entities(Vector2f::ZERO, 3), vec![a, c, b]);
+        assert_eq!(world.nearest_entities(Vector2f::ZERO, 10), vec![a, c, b, d]);
+        assert!(world.nearest_entities(Vector2f::ZERO, 0).is_empty());
+
+        world.set_spatial_cell_size(8.0).unwrap();
+        assert_eq!(
+            world.nearest_entities(Vector2f::new(990.0, 990.0), 1),
+            vec![d]
+        );
+    }
+
+    #[test]
+    fn invalid_cell_sizes_are_rejected() {
+        let mut world = World::new();
+
+        for &cell_size in &[0.0, -8.0, f32::NAN, f32::INFINITY] {
+            assert!(
+                world.set_spatial_cell_size(cell_size).is_err(),
+                "{}",
+                cell_size
+            );
+        }
+        assert_eq!(world.spatial_index.cell_size(), DEFAULT_CELL_SIZE);
+    }
+
+    #[test]
+    fn huge_bounds_and_queries_only_visit_occupied_cells() {
+        let mut world = World::new();
+        world.set_spatial_cell_size(1.0).unwrap();
+
+        let small = spawn(&mut world, 0.0, 0.0);
+        let huge = world
+            .create_entity()
+            .with(Transform::default())
+            .with(Bounds::centered(1e9, 1e9))
+            .build();
+        update(&mut world);
+
+        assert_eq!(
+            world.entities_in_rect(&Rectangle::new(-1e9, -1e9, 2e9, 2e9)),
+            vec![small, huge]
+        );
+        assert_eq!(
+            world.entities_in_radius(Vector2f::new(1e8, 1e8), 1.0),
+            vec![huge]
+        );
+        assert!(world.entities_in_radius(Vector2f::ZERO, -1.0).is_empty());
+        assert_eq!(
+            world.nearest_entities(Vector2f::new(1e9, -1e9), 10),
+            vec![huge, small]
+        );
+
+        world.kill_entity(huge).unwrap();
+        update(&mut world);
+        assert_eq!(
+            world.nearest_entities(Vector2f::new(1e9, -1e9), 10),
+            vec![small]
+        );
+    }
+}
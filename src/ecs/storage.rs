@@ -0,0 +1,389 @@
+use std::any::{Any, TypeId};
+
+use crate::ecs::{Component, Entity, World};
+
+/// A component whose type has been erased, so that components of different
+/// types can be collected together before being added to the world.
+pub(crate) trait AnyComponent: Send + Sync {
+    fn component_type(&self) -> TypeId;
+
+    fn attach_to(self: Box<Self>, world: &mut World, entity: Entity);
+}
+
+impl<T: Component> AnyComponent for T {
+    fn component_type(&self) -> TypeId {
+        TypeId::of::<T>()
+    }
+
+    fn attach_to(self: Box<Self>, world: &mut World, entity: Entity) {
+        world.attach_component(entity, *self);
+    }
+}
+
+pub(crate) type BoxedComponent = Box<dyn AnyComponent>;
+
+/// Adds a component to a list of components that are waiting to be attached
+/// to an entity, replacing any component of the same type in place.
+///
+/// Keeping the components in the order in which they were first given means
+/// that they are attached, and their hooks run, in a deterministic order.
+pub(crate) fn push_component(components: &mut Vec<BoxedComponent>, component: BoxedComponent) {
+    let component_type = component.component_type();
+
+    match components
+        .iter_mut()
+        .find(|existing| existing.component_type() == component_type)
+    {
+        Some(existing) => *existing = component,
+        None => components.push(component),
+    }
+}
+
+/// The operations on a [`SparseSet`] that don't depend on its component type.
+pub(crate) trait AnyStorage: Send + Sync {
+    fn as_any(&self) -> &dyn Any;
+
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+
+    /// Removes the component from the given entity, passing it to `on_remove`
+    /// before it is dropped.
+    fn remove(&mut self, entity: Entity, on_remove: &mut dyn FnMut(&dyn Any)) -> bool;
+
+    fn component_name(&self) -> &'static str;
+
+    fn len(&self) -> usize;
+
+    fn get_any(&self, entity: Entity) -> Option<&dyn Any>;
+
+    fn entities(&self) -> &[Entity];
+
+    /// Moves the component from the given entity onto an entity in another world.
+    fn move_to(&mut self, entity: Entity, world: &mut World, new_entity: Entity) -> bool;
+}
+
+/// The ticks at which a component was added and last changed.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ComponentTicks {
+    /// The tick at which the component was added.
+    pub added: u32,
+
+    /// The tick at which the component was last changed.
+    pub changed: u32,
+}
+
+impl ComponentTicks {
+    fn new(tick: u32) -> Self {
+        Self {
+            added: tick,
+            changed: tick,
+        }
+    }
+
+    /// Returns whether the component was added during the given tick.
+    pub fn is_added(&self, tick: u32) -> bool {
+        self.added == tick
+    }
+
+    /// Returns whether the component was added or changed during the given tick.
+    pub fn is_changed(&self, tick: u32) -> bool {
+        self.changed == tick
+    }
+}
+
+/// The storage for all of the components of a single type.
+///
+/// Components are stored contiguously, without any gaps, and are looked up by
+/// the ID of their entity through a sparse index.
+#[derive(Clone)]
+pub struct SparseSet<T> {
+    /// Maps an entity ID to the index of its component in `dense` and `data`.
+    sparse: Vec<Option<u32>>,
+    dense: Vec<Entity>,
+    data: Vec<T>,
+    ticks: Vec<ComponentTicks>,
+}
+
+impl<T> Default for SparseSet<T> {
+    fn default() -> Self {
+        Self {
+            sparse: Vec::new(),
+            dense: Vec::new(),
+            data: Vec::new(),
+            ticks: Vec::new(),
+        }
+    }
+}
+
+impl<T> SparseSet<T> {
+    /// Returns a new, empty [`SparseSet`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of components in the set.
+    pub fn len(&self) -> usize {
+        self.dense.len()
+    }
+
+    /// Returns whether the set contains no components.
+    pub fn is_empty(&self) -> bool {
+        self.dense.is_empty()
+    }
+
+    /// Returns the entities that have a component in the set.
+    pub fn entities(&self) -> &[Entity] {
+        &self.dense
+    }
+
+    /// Returns whether the given entity has a component in the set.
+    pub fn contains(&self, entity: Entity) -> bool {
+        self.index_of(entity).is_some()
+    }
+
+    /// Returns the component for the given entity.
+    pub fn get(&self, entity: Entity) -> Option<&T> {
+        self.index_of(entity).map(|index| &self.data[index])
+    }
+
+    /// Returns the ticks at which the component for the given entity was added
+    /// and last changed.
+    pub fn ticks(&self, entity: Entity) -> Option<ComponentTicks> {
+        self.index_of(entity).map(|index| self.ticks[index])
+    }
+
+    /// Returns the component for the given entity, marking it as changed
+    /// during the given tick.
+    pub(crate) fn get_mut(&mut self, entity: Entity, tick: u32) -> Option<&mut T> {
+        let index = self.index_of(entity)?;
+
+        self.ticks[index].changed = tick;
+
+        Some(&mut self.data[index])
+    }
+
+    /// Returns pointers to the start of the components and of their ticks,
+    /// which stay valid until the set is next modified.
+    pub(crate) fn data_and_ticks_ptr(&mut self) -> (*mut T, *mut ComponentTicks) {
+        (self.data.as_mut_ptr(), self.ticks.as_mut_ptr())
+    }
+
+    /// Returns an iterator over all of the entities and their components.
+    pub fn iter(&self) -> impl Iterator<Item = (Entity, &T)> {
+        self.dense.iter().copied().zip(self.data.iter())
+    }
+
+    /// Inserts a component for the given entity during the given tick,
+    /// returning the previous component if there was one.
+    ///
+    /// Replacing an existing component marks it as changed rather than added.
+    pub(crate) fn insert(&mut self, entity: Entity, component: T, tick: u32) -> Option<T> {
+        if let Some(index) = self.slot_of(entity) {
+            if self.dense[index] != entity {
+                // The slot belongs to an earlier generation of the entity, so
+                // the component is new to this one.
+                self.dense[index] = entity;
+                self.data[index] = component;
+                self.ticks[index] = ComponentTicks::new(tick);
+                return None;
+            }
+
+            self.ticks[index].changed = tick;
+            return Some(std::mem::replace(&mut self.data[index], component));
+        }
+
+        let id = entity.id() as usize;
+        if self.sparse.len() <= id {
+            self.sparse.resize(id + 1, None);
+        }
+
+        self.sparse[id] = Some(self.dense.len() as u32);
+        self.dense.push(entity);
+        self.data.push(component);
+        self.ticks.push(ComponentTicks::new(tick));
+
+        None
+    }
+
+    /// Returns whether both sets hold equal components for the same entities,
+    /// in the same order.
+    pub(crate) fn same_components(&self, other: &Self) -> bool
+    where
+        T: PartialEq,
+    {
+        self.dense == other.dense && self.data == other.data
+    }
+
+    /// Reserves space for at least the given number of additional components.
+    pub(crate) fn reserve(&mut self, additional: usize) {
+        self.dense.reserve(additional);
+        self.data.reserve(additional);
+        self.ticks.reserve(additional);
+    }
+
+    /// Removes the component for the given entity and returns it, if there was one.
+    pub(crate) fn remove(&mut self, entity: Entity) -> Option<T> {
+        let index = self.index_of(entity)?;
+
+        self.sparse[entity.id() as usize] = None;
+        self.dense.swap_remove(index);
+        let component = self.data.swap_remove(index);
+        self.ticks.swap_remove(index);
+
+        // Fix up the index of the component that was moved into the hole.
+        if let Some(moved) = self.dense.get(index) {
+            self.sparse[moved.id() as usize] = Some(index as u32);
+        }
+
+        Some(component)
+    }
+
+    /// Returns the index of the component for the given entity in the dense arrays.
+    ///
+    /// Stale handles whose ID has since been recycled don't match.
+    pub(crate) fn index_of(&self, entity: Entity) -> Option<usize> {
+        self.slot_of(entity)
+            .filter(|&index| self.dense[index] == entity)
+    }
+
+    /// Returns the index of the component stored under the ID of the given
+    /// entity, whichever generation it belongs to.
+    fn slot_of(&self, entity: Entity) -> Option<usize> {
+        self.sparse
+            .get(entity.id() as usize)
+            .copied()
+            .flatten()
+            .map(|index| index as usize)
+    }
+}
+
+impl<T: Component> AnyStorage for SparseSet<T> {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn remove(&mut self, entity: Entity, on_remove: &mut dyn FnMut(&dyn Any)) -> bool {
+        match SparseSet::remove(self, entity) {
+            Some(component) => {
+                on_remove(&component);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn component_name(&self) -> &'static str {
+        std::any::type_name::<T>()
+    }
+
+    fn len(&self) -> usize {
+        SparseSet::len(self)
+    }
+
+    fn get_any(&self, entity: Entity) -> Option<&dyn Any> {
+        SparseSet::get(self, entity).map(|component| component as &dyn Any)
+    }
+
+    fn entities(&self) -> &[Entity] {
+        SparseSet::entities(self)
+    }
+
+    fn move_to(&mut self, entity: Entity, world: &mut World, new_entity: Entity) -> bool {
+        match SparseSet::remove(self, entity) {
+            Some(component) => {
+                world.insert_moved_component(new_entity, component);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::ecs::Allocator;
+
+    #[test]
+    fn remove_keeps_the_remaining_components_reachable() {
+        let mut allocator = Allocator::new();
+        let entities = (0..4).map(|_| allocator.allocate()).collect::<Vec<_>>();
+
+        let mut set = SparseSet::new();
+        for (value, &entity) in entities.iter().enumerate() {
+            set.insert(entity, value, 0);
+        }
+
+        assert_eq!(set.remove(entities[1]), Some(1));
+        assert_eq!(set.remove(entities[1]), None);
+
+        assert_eq!(set.len(), 3);
+        assert_eq!(set.get(entities[0]), Some(&0));
+        assert_eq!(set.get(entities[2]), Some(&2));
+        assert_eq!(set.get(entities[3]), Some(&3));
+    }
+
+    #[test]
+    fn insert_replaces_existing_components() {
+        let mut allocator = Allocator::new();
+        let entity = allocator.allocate();
+
+        let mut set = SparseSet::new();
+
+        assert_eq!(set.insert(entity, "first", 0), None);
+        assert_eq!(set.insert(entity, "second", 1), Some("first"));
+        assert_eq!(set.len(), 1);
+        assert_eq!(set.get(entity), Some(&"second"));
+        assert_eq!(
+            set.ticks(entity),
+            Some(ComponentTicks {
+                added: 0,
+                changed: 1
+            })
+        );
+    }
+
+    #[test]
+    fn stale_entities_do_not_see_components_of_recycled_ids() {
+        let mut allocator = Allocator::new();
+        let stale = allocator.allocate();
+        allocator.kill(stale).unwrap();
+        let recycled = allocator.allocate();
+        assert_eq!(recycled.id(), stale.id());
+
+        let mut set = SparseSet::new();
+        set.insert(recycled, "recycled", 0);
+
+        assert!(!set.contains(stale));
+        assert_eq!(set.get(stale), None);
+        assert_eq!(set.ticks(stale), None);
+        assert_eq!(set.remove(stale), None);
+        assert_eq!(set.get(recycled), Some(&"recycled"));
+    }
+
+    #[test]
+    fn insert_overwrites_components_left_by_earlier_generations() {
+        let mut allocator = Allocator::new();
+        let stale = allocator.allocate();
+        allocator.kill(stale).unwrap();
+        let recycled = allocator.allocate();
+
+        let mut set = SparseSet::new();
+        set.insert(stale, "stale", 0);
+
+        assert_eq!(set.insert(recycled, "recycled", 1), None);
+        assert_eq!(set.len(), 1);
+        assert_eq!(set.get(recycled), Some(&"recycled"));
+        assert_eq!(
+            set.ticks(recycled),
+            Some(ComponentTicks {
+                added: 1,
+                changed: 1
+            })
+        );
+    }
+}
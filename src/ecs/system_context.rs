@@ -0,0 +1,159 @@
+use std::any::{type_name, Any, TypeId};
+use std::collections::HashMap;
+use std::ptr::NonNull;
+
+use crate::ecs::{
+    AnyStorage, Commands, Component, Entity, Query, QueryFilter, QueryIter, Resource, SparseSet,
+    Storages, SystemAccess,
+};
+use crate::error::EcsError;
+
+/// Pointers to the storages and resources of a [`World`](crate::ecs::World),
+/// shared between the systems that run in parallel with each other.
+pub(crate) struct SharedWorld {
+    pub(crate) storages: HashMap<TypeId, NonNull<dyn AnyStorage>>,
+    pub(crate) resources: HashMap<TypeId, NonNull<dyn Any + Send + Sync>>,
+    pub(crate) entities: NonNull<[Entity]>,
+    pub(crate) change_tick: u32,
+}
+
+// SAFETY: The world is only accessed through a `SystemContext`, which checks
+// that each system stays within its declared access, and systems are only run
+// in parallel when their declared accesses don't conflict.
+unsafe impl Send for SharedWorld {}
+unsafe impl Sync for SharedWorld {}
+
+impl Storages for &SharedWorld {
+    fn storage<T: Component>(&mut self) -> Option<NonNull<SparseSet<T>>> {
+        self.storages
+            .get(&TypeId::of::<T>())
+            .map(|storage| storage.cast::<SparseSet<T>>())
+    }
+
+    fn change_tick(&self) -> u32 {
+        self.change_tick
+    }
+}
+
+/// The view of the world given to a system created with [`System::parallel`](crate::ecs::System::parallel).
+///
+/// A system can only access the components and resources that it declared.
+pub struct SystemContext<'w> {
+    name: &'static str,
+    world: &'w SharedWorld,
+    access: &'w SystemAccess,
+    pub(crate) commands: Commands,
+}
+
+impl<'w> SystemContext<'w> {
+    pub(crate) fn new(
+        name: &'static str,
+        world: &'w SharedWorld,
+        access: &'w SystemAccess,
+    ) -> Self {
+        Self {
+            name,
+            world,
+            access,
+            commands: Commands::new(),
+        }
+    }
+
+    /// Returns the name of the running system.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Returns all of the entities in the world.
+    pub fn entities(&self) -> &[Entity] {
+        // SAFETY: Entities are only created and deleted while no systems are running.
+        unsafe { self.world.entities.as_ref() }
+    }
+
+    /// Returns an iterator over all of the entities matching the given [`Query`].
+    ///
+    /// Returns an error if the query accesses a component that the system did not declare.
+    pub fn query<Q: Query>(&mut self) -> Result<QueryIter<'_, Q>, EcsError> {
+        self.query_filtered::<Q, ()>()
+    }
+
+    /// Returns an iterator over all of the entities matching the given [`Query`]
+    /// and [`QueryFilter`].
+    ///
+    /// Returns an error if the query accesses a component that the system did not declare.
+    pub fn query_filtered<Q: Query, F: QueryFilter>(
+        &mut self,
+    ) -> Result<QueryIter<'_, Q, F>, EcsError> {
+        let (access, filter_access) = QueryIter::<Q, F>::access();
+
+        let undeclared = access
+            .undeclared_in(&self.access.components)
+            .or_else(|| filter_access.undeclared_in(&self.access.components));
+        if let Some(type_name) = undeclared {
+            return Err(EcsError::UndeclaredAccess {
+                system: self.name,
+                type_name,
+            });
+        }
+
+        // SAFETY: The query stays within the access declared by the system, and
+        // the scheduler does not run systems with conflicting access in parallel.
+        unsafe {
+            let entities = self.world.entities.as_ref();
+            QueryIter::from_storages(&mut self.world, entities)
+        }
+    }
+
+    /// Returns a reference to the resource of type `T`.
+    ///
+    /// Returns an error if the system did not declare access to the resource,
+    /// or if the world does not contain it.
+    pub fn resource<T: Resource>(&self) -> Result<&T, EcsError> {
+        let type_id = TypeId::of::<T>();
+        if !self.access.resources.is_read(type_id) && !self.access.resources.is_written(type_id) {
+            return Err(self.undeclared::<T>());
+        }
+
+        // SAFETY: The system declared access to the resource, and the scheduler
+        // does not run systems with conflicting access in parallel.
+        self.world
+            .resources
+            .get(&type_id)
+            .map(|resource| unsafe { &*(resource.as_ptr() as *const T) })
+            .ok_or_else(|| EcsError::MissingResource(type_name::<T>()))
+    }
+
+    /// Returns a mutable reference to the resource of type `T`.
+    ///
+    /// Returns an error if the system did not declare write access to the
+    /// resource, or if the world does not contain it.
+    pub fn resource_mut<T: Resource>(&mut self) -> Result<&mut T, EcsError> {
+        let type_id = TypeId::of::<T>();
+        if !self.access.resources.is_written(type_id) {
+            return Err(self.undeclared::<T>());
+        }
+
+        // SAFETY: The system declared write access to the resource, and the
+        // scheduler does not run systems with conflicting access in parallel.
+        self.world
+            .resources
+            .get(&type_id)
+            .map(|resource| unsafe { &mut *(resource.as_ptr() as *mut T) })
+            .ok_or_else(|| EcsError::MissingResource(type_name::<T>()))
+    }
+
+    /// Returns the [`Commands`] buffer for recording deferred changes to the world.
+    ///
+    /// The commands recorded by systems that run in parallel are applied in
+    /// the order in which the systems would have run sequentially.
+    pub fn commands(&mut self) -> &mut Commands {
+        &mut self.commands
+    }
+
+    fn undeclared<T>(&self) -> EcsError {
+        EcsError::UndeclaredAccess {
+            system: self.name,
+            type_name: type_name::<T>(),
+        }
+    }
+}
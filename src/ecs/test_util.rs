@@ -0,0 +1,32 @@
+//! Components and worlds shared by the tests of the `ecs` modules.
+
+use serde::{Deserialize, Serialize};
+
+use crate::ecs::{Component, Entity, EntityMap, MapEntities, World};
+use crate::error::SceneError;
+
+#[derive(Debug, Clone, PartialEq, Hash, Serialize, Deserialize)]
+pub(crate) struct Health(pub(crate) u32);
+
+impl Component for Health {}
+
+/// A component that refers to another entity.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub(crate) struct Target(pub(crate) Entity);
+
+impl Component for Target {}
+
+impl MapEntities for Target {
+    fn map_entities(&mut self, entities: &EntityMap) -> Result<(), SceneError> {
+        self.0 = entities.get(self.0)?;
+        Ok(())
+    }
+}
+
+/// Returns a world with [`Health`] and [`Target`] registered for scenes and prefabs.
+pub(crate) fn registered_world() -> World {
+    let mut world = World::new();
+    world.register_component::<Health>("Health");
+    world.register_component_with_entities::<Target>("Target");
+    world
+}
@@ -0,0 +1,347 @@
+use std::collections::HashSet;
+use std::hash::{Hash, Hasher};
+
+use serde::{Deserialize, Serialize};
+
+use crate::ecs::{hash_f32, Changed, Children, Component, Entity, Parent, World};
+use crate::Vector2f;
+
+/// The position, rotation and scale of an entity, relative to its parent.
+///
+/// Entities without a parent are positioned relative to the world.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Transform {
+    /// The position of the entity.
+    pub position: Vector2f,
+
+    /// The rotation of the entity, in radians.
+    pub rotation: f32,
+
+    /// The scale of the entity.
+    pub scale: Vector2f,
+}
+
+impl Transform {
+    /// The transform that leaves an entity where it is.
+    pub const IDENTITY: Self = Self {
+        position: Vector2f::ZERO,
+        rotation: 0.0,
+        scale: Vector2f::UNIT,
+    };
+
+    /// Returns a new [`Transform`] at the given position.
+    pub fn from_position(position: Vector2f) -> Self {
+        Self {
+            position,
+            ..Self::IDENTITY
+        }
+    }
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl Hash for Transform {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        for value in [
+            self.position.x,
+            self.position.y,
+            self.rotation,
+            self.scale.x,
+            self.scale.y,
+        ] {
+            hash_f32(value, state);
+        }
+    }
+}
+
+impl Component for Transform {}
+
+/// The position, rotation and scale of an entity, relative to the world.
+///
+/// This is computed from the [`Transform`]s of the entity and its ancestors
+/// once per update, and is added to every entity that has a [`Transform`].
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct GlobalTransform {
+    /// The position of the entity.
+    pub position: Vector2f,
+
+    /// The rotation of the entity, in radians.
+    pub rotation: f32,
+
+    /// The scale of the entity.
+    pub scale: Vector2f,
+}
+
+impl GlobalTransform {
+    /// The transform of the world itself.
+    pub const IDENTITY: Self = Self {
+        position: Vector2f::ZERO,
+        rotation: 0.0,
+        scale: Vector2f::UNIT,
+    };
+
+    /// Returns the global transform of a child with the given local transform.
+    pub fn mul_transform(&self, transform: &Transform) -> Self {
+        let scaled = self.scale * transform.position;
+        let (sin, cos) = self.rotation.sin_cos();
+
+        Self {
+            position: self.position
+                + Vector2f::new(
+                    scaled.x * cos - scaled.y * sin,
+                    scaled.x * sin + scaled.y * cos,
+                ),
+            rotation: self.rotation + transform.rotation,
+            scale: self.scale * transform.scale,
+        }
+    }
+}
+
+impl Default for GlobalTransform {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl Hash for GlobalTransform {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        for value in [
+            self.position.x,
+            self.position.y,
+            self.rotation,
+            self.scale.x,
+            self.scale.y,
+        ] {
+            hash_f32(value, state);
+        }
+    }
+}
+
+impl Component for GlobalTransform {}
+
+impl World {
+    /// Computes the [`GlobalTransform`] of the entities whose [`Transform`] or
+    /// [`Parent`] was added, changed or removed during the current tick, by
+    /// walking down the hierarchy from them. The rest of the hierarchy is left
+    /// as it is.
+    ///
+    /// Entities without a [`Transform`] have their [`GlobalTransform`] removed,
+    /// and pass the transform of their parent through to their children unchanged.
+    ///
+    /// This is run by the engine once per update.
+    pub fn propagate_transforms(&mut self) {
+        let mut dirty = self
+            .removed::<Transform>()
+            .chain(self.removed::<Parent>())
+            .chain(self.removed::<GlobalTransform>())
+            .collect::<HashSet<_>>();
+        dirty.extend(
+            self.query_filtered::<(), Changed<Transform>>()
+                .into_iter()
+                .flatten()
+                .map(|(entity, _)| entity),
+        );
+        dirty.extend(
+            self.query_filtered::<(), Changed<Parent>>()
+                .into_iter()
+                .flatten()
+                .map(|(entity, _)| entity),
+        );
+
+        // Walking down from an entity also updates its descendants, so only
+        // start from the entities without a dirty ancestor.
+        let mut pending = dirty
+            .iter()
+            .copied()
+            .filter(|&entity| self.is_alive(entity))
+            .filter(|&entity| {
+                !self
+                    .ancestors(entity)
+                    .any(|ancestor| dirty.contains(&ancestor))
+            })
+            .map(|entity| (entity, self.inherited_global_transform(entity)))
+            .collect::<Vec<_>>();
+        pending.sort_by_key(|(entity, _)| entity.to_bits());
+
+        while let Some((entity, parent)) = pending.pop() {
+            let global = match self.get_component::<Transform>(entity) {
+                Some(transform) => {
+                    let global = parent.mul_transform(transform);
+                    self.set_global_transform(entity, global);
+                    global
+                }
+                None => {
+                    if self.has_component::<GlobalTransform>(entity) {
+                        self.remove_component::<GlobalTransform>(entity);
+                    }
+                    parent
+                }
+            };
+
+            if let Some(children) = self.get_component::<Children>(entity) {
+                pending.extend(children.iter().map(|child| (child, global)));
+            }
+        }
+    }
+
+    /// Returns the parent, grandparent and so on of the given entity.
+    fn ancestors(&self, entity: Entity) -> impl Iterator<Item = Entity> + '_ {
+        std::iter::successors(self.parent(entity), move |&ancestor| self.parent(ancestor))
+    }
+
+    /// Returns the global transform that the given entity inherits from its
+    /// closest ancestor with a [`GlobalTransform`].
+    fn inherited_global_transform(&self, entity: Entity) -> GlobalTransform {
+        self.ancestors(entity)
+            .find_map(|ancestor| self.get_component::<GlobalTransform>(ancestor).copied())
+            .unwrap_or(GlobalTransform::IDENTITY)
+    }
+
+    /// Writes the given global transform, leaving the component untouched if
+    /// it hasn't changed so that it isn't marked as changed.
+    fn set_global_transform(&mut self, entity: Entity, global: GlobalTransform) {
+        if self.get_component::<GlobalTransform>(entity) != Some(&global) {
+            self.attach_component(entity, global);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::f32::consts::FRAC_PI_2;
+
+    fn assert_approx_eq(left: Vector2f, right: Vector2f) {
+        assert!(
+            (left.x - right.x).abs() < 1e-5 && (left.y - right.y).abs() < 1e-5,
+            "{:?} != {:?}",
+            left,
+            right
+        );
+    }
+
+    #[test]
+    fn global_transforms_are_propagated_down_the_hierarchy() {
+        let mut world = World::new();
+
+        let player = world
+            .create_entity()
+            .with(Transform {
+                position: Vector2f::new(10.0, 20.0),
+                rotation: FRAC_PI_2,
+                scale: Vector2f::new(2.0, 2.0),
+            })
+            .build();
+        let hand = world.create_entity().build();
+        let weapon = world
+            .create_entity()
+            .with(Transform::from_position(Vector2f::new(5.0, 0.0)))
+            .build();
+
+        world.set_parent(hand, player).unwrap();
+        world.set_parent(weapon, hand).unwrap();
+
+        world.propagate_transforms();
+
+        let player_global = world.get_component::<GlobalTransform>(player).unwrap();
+        assert_eq!(player_global.position, Vector2f::new(10.0, 20.0));
+
+        assert!(!world.has_component::<GlobalTransform>(hand));
+
+        let weapon_global = *world.get_component::<GlobalTransform>(weapon).unwrap();
+        assert_approx_eq(weapon_global.position, Vector2f::new(10.0, 30.0));
+        assert_eq!(weapon_global.rotation, FRAC_PI_2);
+        assert_eq!(weapon_global.scale, Vector2f::new(2.0, 2.0));
+    }
+
+    #[test]
+    fn only_changed_subtrees_are_propagated() {
+        let mut world = World::new();
+
+        let parent = world
+            .create_entity()
+            .with(Transform::from_position(Vector2f::new(10.0, 0.0)))
+            .build();
+        let child = world
+            .create_entity()
+            .with(Transform::from_position(Vector2f::new(1.0, 0.0)))
+            .build();
+        let other = world
+            .create_entity()
+            .with(Transform::from_position(Vector2f::new(100.0, 0.0)))
+            .build();
+        world.set_parent(child, parent).unwrap();
+
+        world.propagate_transforms();
+        world.clear_trackers();
+
+        let global = |world: &World, entity| {
+            world
+                .get_component::<GlobalTransform>(entity)
+                .map(|global| global.position)
+        };
+
+        // Global transforms outside of the changed subtrees are not recomputed.
+        world
+            .get_component_mut::<GlobalTransform>(other)
+            .unwrap()
+            .position = Vector2f::ZERO;
+        world
+            .get_component_mut::<Transform>(parent)
+            .unwrap()
+            .position = Vector2f::new(20.0, 0.0);
+        world.propagate_transforms();
+        assert_eq!(global(&world, child), Some(Vector2f::new(21.0, 0.0)));
+        assert_eq!(global(&world, other), Some(Vector2f::ZERO));
+        world.clear_trackers();
+
+        world.set_parent(child, other).unwrap();
+        world.propagate_transforms();
+        assert_eq!(global(&world, child), Some(Vector2f::new(1.0, 0.0)));
+        world.clear_trackers();
+
+        world
+            .get_component_mut::<Transform>(other)
+            .unwrap()
+            .position = Vector2f::new(100.0, 0.0);
+        world.propagate_transforms();
+        assert_eq!(global(&world, child), Some(Vector2f::new(101.0, 0.0)));
+        world.clear_trackers();
+
+        world.remove_parent(child).unwrap();
+        world.remove_component::<Transform>(parent);
+        world.propagate_transforms();
+        assert_eq!(global(&world, child), Some(Vector2f::new(1.0, 0.0)));
+        assert_eq!(global(&world, parent), None);
+    }
+
+    #[test]
+    fn unchanged_global_transforms_are_not_marked_as_changed() {
+        let mut world = World::new();
+
+        let entity = world
+            .create_entity()
+            .with(Transform::from_position(Vector2f::new(1.0, 1.0)))
+            .build();
+
+        world.propagate_transforms();
+        assert!(world.is_changed::<GlobalTransform>(entity));
+
+        world.clear_trackers();
+        world.propagate_transforms();
+        assert!(!world.is_changed::<GlobalTransform>(entity));
+
+        world
+            .get_component_mut::<Transform>(entity)
+            .unwrap()
+            .position = Vector2f::ZERO;
+        world.propagate_transforms();
+        assert!(world.is_changed::<GlobalTransform>(entity));
+    }
+}
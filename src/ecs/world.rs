@@ -1,95 +1,585 @@
-use core::any::{Any, TypeId};
+use std::any::{type_name, TypeId};
 use std::collections::HashMap;
-use std::iter::FromIterator;
+use std::ptr::NonNull;
 
-use crate::ecs::{Allocator, Component, Entity, EntityBuilder};
+use crate::ecs::{
+    Allocator, AnyStorage, BoxedComponent, Bundle, Commands, Component, ComponentRegistry,
+    ComponentTicks, DebugComponents, Entity, EntityBuilder, Hooks, NameIndex, Prefabs, Query,
+    QueryFilter, QueryIter, Resource, Resources, SharedWorld, SnapshotRegistry, SparseSet,
+    SpatialIndex, DEFAULT_CELL_SIZE,
+};
+use crate::error::EcsError;
 
-pub(crate) struct World {
+/// The container for all of the entities, components and resources in a game.
+pub struct World {
     pub(crate) entities: Vec<Entity>,
-    components: HashMap<TypeId, Vec<Option<Box<dyn Any>>>>,
+
+    /// Maps an entity ID to the index of the entity in `entities`.
+    pub(crate) entity_indices: Vec<Option<u32>>,
+
+    pub(crate) components: HashMap<TypeId, Box<dyn AnyStorage>>,
     pub(crate) allocator: Allocator,
+    pub(crate) commands: Commands,
+    resources: Resources,
+
+    /// The current tick, used to track when components are added and changed.
+    pub(crate) change_tick: u32,
+
+    /// The entities that have had a component removed during the current tick.
+    removed: HashMap<TypeId, Vec<Entity>>,
+
+    /// The component types that can be saved to and loaded from scenes.
+    pub(crate) registry: ComponentRegistry,
+
+    /// The prefabs that entities can be spawned from.
+    pub(crate) prefabs: Prefabs,
+
+    /// The callbacks that are run when components are added or removed.
+    pub(crate) hooks: Hooks,
+
+    /// The entities with each [`Name`](crate::ecs::Name).
+    pub(crate) names: NameIndex,
+
+    /// The component types whose values are included when dumping the world.
+    pub(crate) debug_components: DebugComponents,
+
+    /// The component types that are included in snapshots of the world.
+    pub(crate) snapshot_registry: SnapshotRegistry,
+
+    /// The entities with [`Bounds`](crate::ecs::Bounds), bucketed by position.
+    pub(crate) spatial_index: SpatialIndex,
 }
 
-impl World {
-    pub(crate) const DEFAULT_STORAGE_CAPACITY: usize = 256;
+impl Default for World {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
+impl World {
+    /// Returns a new, empty [`World`].
     pub fn new() -> Self {
         Self {
             entities: Vec::new(),
+            entity_indices: Vec::new(),
             components: HashMap::new(),
             allocator: Allocator::new(),
+            commands: Commands::new(),
+            resources: Resources::new(),
+            change_tick: 0,
+            removed: HashMap::new(),
+            registry: ComponentRegistry::new(),
+            prefabs: Prefabs::new(),
+            hooks: Hooks::new(),
+            names: NameIndex::new(),
+            debug_components: DebugComponents::new(),
+            snapshot_registry: SnapshotRegistry::new(),
+            spatial_index: SpatialIndex::new(DEFAULT_CELL_SIZE),
         }
     }
 
-    pub fn create_entity<'a>(&'a mut self) -> EntityBuilder<'a> {
+    /// Returns a new, empty [`World`] with the same registered components,
+    /// hooks, prefabs and spatial cell size as this one.
+    ///
+    /// The hooks themselves are shared with this world, while the rest are
+    /// copied, so later registrations only apply to the world they're made on.
+    pub(crate) fn new_sibling(&self) -> Self {
+        Self {
+            registry: self.registry.clone(),
+            prefabs: self.prefabs.copy(),
+            hooks: self.hooks.clone(),
+            debug_components: self.debug_components.clone(),
+            snapshot_registry: self.snapshot_registry.clone(),
+            spatial_index: SpatialIndex::new(self.spatial_index.cell_size()),
+            ..Self::new()
+        }
+    }
+
+    /// Returns all of the entities in the world.
+    ///
+    /// Deleting an entity moves the last entity into its place, so the order
+    /// is not preserved across deletions.
+    pub fn entities(&self) -> &[Entity] {
+        &self.entities
+    }
+
+    /// Returns an [`EntityBuilder`] for creating a new entity in the world.
+    pub fn create_entity(&mut self) -> EntityBuilder<'_> {
         EntityBuilder::new(self)
     }
 
-    pub fn kill_entity(&mut self, entity: Entity) {
-        self.entities.retain(|e| *e != entity);
+    pub(crate) fn spawn<I>(&mut self, components: I) -> Entity
+    where
+        I: IntoIterator<Item = BoxedComponent>,
+    {
+        let entity = self.allocator.allocate();
+
+        for component in components {
+            component.attach_to(self, entity);
+        }
+
+        self.push_entity(entity);
+
+        entity
+    }
+
+    /// Spawns an entity for each of the given bundles of components, returning
+    /// the new entities in the same order.
+    ///
+    /// Space for the entities and their components is reserved up front.
+    pub fn spawn_batch<B, I>(&mut self, bundles: I) -> Vec<Entity>
+    where
+        B: Bundle,
+        I: IntoIterator<Item = B>,
+    {
+        let bundles = bundles.into_iter();
+        let (additional, _) = bundles.size_hint();
+
+        self.allocator.reserve(additional);
+        self.entities.reserve(additional);
+        B::reserve(self, additional);
+
+        let mut spawned = Vec::with_capacity(additional);
+        for bundle in bundles {
+            let entity = self.allocator.allocate();
+            bundle.attach_to(self, entity);
+            self.push_entity(entity);
+            spawned.push(entity);
+        }
+
+        spawned
+    }
+
+    /// Deletes the given entity, along with all of its components.
+    ///
+    /// The entity is removed from its parent, and its children are left without a parent.
+    ///
+    /// Returns an error if the entity has already been deleted.
+    pub fn kill_entity(&mut self, entity: Entity) -> Result<(), EcsError> {
+        if !self.is_alive(entity) {
+            return Err(EcsError::DeadEntity(entity));
+        }
+
+        self.clear_entity(entity);
+
+        self.allocator.kill(entity)
+    }
+
+    /// Deletes all of the given entities, along with their components.
+    ///
+    /// Stops at the first entity that is not alive, or that appears more than
+    /// once, and returns an error for it. Any entities before it will have
+    /// already been deleted.
+    pub fn kill_entities<I>(&mut self, entities: I) -> Result<(), EcsError>
+    where
+        I: IntoIterator<Item = Entity>,
+    {
+        let mut killed = Vec::new();
+        let mut result = Ok(());
+
+        for entity in entities {
+            if !self.is_alive(entity) || !self.clear_entity(entity) {
+                result = Err(EcsError::DeadEntity(entity));
+                break;
+            }
+
+            killed.push(entity);
+        }
+
+        self.allocator.kill_many(killed)?;
+
+        result
+    }
+
+    /// Removes all of the components of the given entity, and removes it from
+    /// the list of entities.
+    ///
+    /// Returns `false` if the entity had already been removed.
+    fn clear_entity(&mut self, entity: Entity) -> bool {
+        if !self.remove_from_entities(entity) {
+            return false;
+        }
+
+        self.hooks.run_on_despawn(entity, &mut self.commands);
+
+        self.detach_from_hierarchy(entity);
+
+        // Dead entities shouldn't be found until the index is next updated.
+        self.spatial_index.remove(entity);
+
+        let hooks = &mut self.hooks;
+        let names = &mut self.names;
+        let commands = &mut self.commands;
+        for (component_type, storage) in self.components.iter_mut() {
+            let was_removed = storage.remove(entity, &mut |component| {
+                names.remove(entity, component);
+                hooks.run_on_remove(*component_type, entity, component, commands)
+            });
+            if was_removed {
+                self.removed
+                    .entry(*component_type)
+                    .or_default()
+                    .push(entity);
+            }
+        }
+
+        true
+    }
+
+    /// Removes the given entity from the list of entities, moving the last
+    /// entity into its place.
+    ///
+    /// Returns `false` if the entity was not in the list.
+    pub(crate) fn remove_from_entities(&mut self, entity: Entity) -> bool {
+        let Some(index) = self.take_entity_index(entity) else {
+            return false;
+        };
 
-        let all_component_types = Vec::from_iter(self.components.keys().map(Clone::clone));
-        for component_type in all_component_types {
-            self.remove_component(entity, component_type);
+        self.entities.swap_remove(index);
+        if let Some(moved) = self.entities.get(index) {
+            self.entity_indices[moved.id() as usize] = Some(index as u32);
         }
 
-        self.allocator.kill(entity);
+        true
     }
 
+    fn push_entity(&mut self, entity: Entity) {
+        let id = entity.id() as usize;
+        if self.entity_indices.len() <= id {
+            self.entity_indices.resize(id + 1, None);
+        }
+
+        self.entity_indices[id] = Some(self.entities.len() as u32);
+        self.entities.push(entity);
+    }
+
+    fn take_entity_index(&mut self, entity: Entity) -> Option<usize> {
+        self.entity_indices
+            .get_mut(entity.id() as usize)
+            .and_then(Option::take)
+            .map(|index| index as usize)
+    }
+
+    /// Applies all of the pending [`Commands`] to the world.
+    ///
+    /// Commands recorded by hooks while the commands are being applied are
+    /// applied as well.
+    pub fn apply_commands(&mut self) -> Result<(), EcsError> {
+        while !self.commands.is_empty() {
+            let mut commands = std::mem::take(&mut self.commands);
+            commands.apply(self)?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns whether the given entity is alive.
+    ///
+    /// An entity is no longer alive once it has been deleted, even if its ID
+    /// has since been reused by another entity.
+    pub fn is_alive(&self, entity: Entity) -> bool {
+        self.allocator.is_alive(entity)
+    }
+
+    /// Returns whether the given entity has a component of type `T`.
+    ///
+    /// Returns `false` if the entity is not alive.
     pub fn has_component<T: Component>(&self, entity: Entity) -> bool {
         self.get_component::<T>(entity).is_some()
     }
 
+    /// Returns the component of type `T` for the given entity.
+    ///
+    /// Returns `None` if the entity is not alive.
     pub fn get_component<T: Component>(&self, entity: Entity) -> Option<&T> {
-        let component_type = TypeId::of::<T>();
-        let components = self.components.get(&component_type)?;
+        if !self.is_alive(entity) {
+            return None;
+        }
 
-        components[entity.id() as usize]
-            .as_ref()
-            .and_then(|component| component.downcast_ref::<T>())
+        self.storage::<T>()?.get(entity)
     }
 
+    /// Returns the component of type `T` for the given entity.
+    ///
+    /// Returns `None` if the entity is not alive.
     pub fn get_component_mut<T: Component>(&mut self, entity: Entity) -> Option<&mut T> {
-        let component_type = TypeId::of::<T>();
-        let components = self.components.get_mut(&component_type)?;
+        if !self.is_alive(entity) {
+            return None;
+        }
 
-        components[entity.id() as usize]
-            .as_mut()
-            .and_then(|component| component.downcast_mut::<T>())
+        let tick = self.change_tick;
+        self.storage_mut::<T>()?.get_mut(entity, tick)
     }
 
-    pub fn attach_component(
+    /// Returns whether the component of type `T` was added to the given entity
+    /// during the current tick.
+    pub fn is_added<T: Component>(&self, entity: Entity) -> bool {
+        self.component_ticks::<T>(entity)
+            .is_some_and(|ticks| ticks.is_added(self.change_tick))
+    }
+
+    /// Returns whether the component of type `T` on the given entity was added
+    /// or mutably accessed during the current tick.
+    pub fn is_changed<T: Component>(&self, entity: Entity) -> bool {
+        self.component_ticks::<T>(entity)
+            .is_some_and(|ticks| ticks.is_changed(self.change_tick))
+    }
+
+    fn component_ticks<T: Component>(&self, entity: Entity) -> Option<ComponentTicks> {
+        if !self.is_alive(entity) {
+            return None;
+        }
+
+        self.storage::<T>()?.ticks(entity)
+    }
+
+    /// Returns an iterator over the entities that have had their component of
+    /// type `T` removed during the current tick, including by being deleted.
+    pub fn removed<T: Component>(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.removed
+            .get(&TypeId::of::<T>())
+            .into_iter()
+            .flatten()
+            .copied()
+    }
+
+    /// Advances to the next tick, clearing all of the added, changed and
+    /// removed components from the current tick.
+    pub fn clear_trackers(&mut self) {
+        for removed in self.removed.values_mut() {
+            removed.clear();
+        }
+
+        self.change_tick = self.change_tick.wrapping_add(1);
+    }
+
+    /// Inserts a component on the given entity, returning the previous
+    /// component of the same type, if there was one.
+    ///
+    /// Returns an error if the entity is not alive.
+    pub fn insert_component<T: Component>(
         &mut self,
         entity: Entity,
-        component_type: TypeId,
-        component: Box<dyn Any>,
-    ) {
-        let components = self
+        component: T,
+    ) -> Result<Option<T>, EcsError> {
+        if !self.is_alive(entity) {
+            return Err(EcsError::DeadEntity(entity));
+        }
+
+        Ok(self.attach_component(entity, component))
+    }
+
+    /// Removes the component of type `T` from the given entity and returns it.
+    ///
+    /// Returns `None` if the entity is not alive or does not have a component of type `T`.
+    pub fn remove_component<T: Component>(&mut self, entity: Entity) -> Option<T> {
+        self.take_component(entity).ok()
+    }
+
+    /// Removes the component of type `T` from the given entity and returns it.
+    ///
+    /// Returns an error if the entity is not alive or does not have a component of type `T`.
+    pub fn take_component<T: Component>(&mut self, entity: Entity) -> Result<T, EcsError> {
+        if !self.is_alive(entity) {
+            return Err(EcsError::DeadEntity(entity));
+        }
+
+        let component = self
+            .storage_mut::<T>()
+            .and_then(|storage| storage.remove(entity))
+            .ok_or_else(|| EcsError::MissingComponent {
+                entity,
+                component: type_name::<T>(),
+            })?;
+
+        self.record_removal(TypeId::of::<T>(), entity);
+        self.names.remove(entity, &component);
+        self.hooks
+            .run_on_remove(TypeId::of::<T>(), entity, &component, &mut self.commands);
+
+        Ok(component)
+    }
+
+    /// Returns the storage for components of type `T`, if any have been added
+    /// to the world.
+    pub fn storage<T: Component>(&self) -> Option<&SparseSet<T>> {
+        self.components
+            .get(&TypeId::of::<T>())
+            .and_then(|storage| storage.as_any().downcast_ref::<SparseSet<T>>())
+    }
+
+    /// Returns pointers to the storages and resources of the world, so that
+    /// they can be shared between systems running in parallel.
+    pub(crate) fn share(&mut self) -> SharedWorld {
+        SharedWorld {
+            storages: self
+                .components
+                .iter_mut()
+                .map(|(&component_type, storage)| (component_type, NonNull::from(&mut **storage)))
+                .collect(),
+            resources: self.resources.pointers(),
+            entities: NonNull::from(self.entities.as_slice()),
+            change_tick: self.change_tick,
+        }
+    }
+
+    pub(crate) fn storage_mut<T: Component>(&mut self) -> Option<&mut SparseSet<T>> {
+        self.components
+            .get_mut(&TypeId::of::<T>())
+            .and_then(|storage| storage.as_any_mut().downcast_mut::<SparseSet<T>>())
+    }
+
+    /// Returns an iterator over all of the entities matching the given [`Query`].
+    pub fn query<Q: Query>(&mut self) -> Result<QueryIter<'_, Q>, EcsError> {
+        QueryIter::new(self)
+    }
+
+    /// Returns an iterator over all of the entities matching the given [`Query`]
+    /// and [`QueryFilter`].
+    pub fn query_filtered<Q: Query, F: QueryFilter>(
+        &mut self,
+    ) -> Result<QueryIter<'_, Q, F>, EcsError> {
+        QueryIter::new(self)
+    }
+
+    /// Inserts a resource into the world, returning the previous resource of
+    /// the same type, if there was one.
+    pub fn insert_resource<T: Resource>(&mut self, resource: T) -> Option<T> {
+        self.resources.insert(resource)
+    }
+
+    /// Returns whether the world contains a resource of type `T`.
+    pub fn has_resource<T: Resource>(&self) -> bool {
+        self.resources.contains::<T>()
+    }
+
+    /// Returns a reference to the resource of type `T`.
+    ///
+    /// Returns an error if the world does not contain a resource of type `T`.
+    pub fn resource<T: Resource>(&self) -> Result<&T, EcsError> {
+        self.resources.get()
+    }
+
+    /// Returns a mutable reference to the resource of type `T`.
+    ///
+    /// Returns an error if the world does not contain a resource of type `T`.
+    pub fn resource_mut<T: Resource>(&mut self) -> Result<&mut T, EcsError> {
+        self.resources.get_mut()
+    }
+
+    /// Removes the resource of type `T` from the world and returns it, if there was one.
+    pub fn remove_resource<T: Resource>(&mut self) -> Option<T> {
+        self.resources.remove()
+    }
+
+    /// Temporarily removes the resource of type `T` from the world so that it
+    /// can be used alongside the rest of the world, such as in a query.
+    ///
+    /// Returns an error if the world does not contain a resource of type `T`.
+    pub fn resource_scope<T, F, U>(&mut self, f: F) -> Result<U, EcsError>
+    where
+        T: Resource,
+        F: FnOnce(&mut World, &mut T) -> U,
+    {
+        let mut resource = self
+            .remove_resource::<T>()
+            .ok_or_else(|| EcsError::MissingResource(type_name::<T>()))?;
+
+        let result = f(self, &mut resource);
+
+        self.insert_resource(resource);
+
+        Ok(result)
+    }
+
+    pub(crate) fn attach_component<T: Component>(
+        &mut self,
+        entity: Entity,
+        component: T,
+    ) -> Option<T> {
+        let tick = self.change_tick;
+        let component_type = TypeId::of::<T>();
+
+        let storage = self
             .components
             .entry(component_type)
-            .or_insert(Vec::with_capacity(Self::DEFAULT_STORAGE_CAPACITY));
+            .or_insert_with(|| Box::new(SparseSet::<T>::new()))
+            .as_any_mut()
+            .downcast_mut::<SparseSet<T>>()
+            .unwrap();
+        let previous = storage.insert(entity, component, tick);
 
-        if components.len() <= entity.id() as usize {
-            components.resize_with(
-                components.len() + Self::DEFAULT_STORAGE_CAPACITY,
-                Default::default,
-            );
+        if let Some(previous) = &previous {
+            self.names.remove(entity, previous);
         }
+        self.names.insert(entity, storage.get(entity).unwrap());
 
-        components[entity.id() as usize] = Some(component);
-    }
+        if self.hooks.has_component_hooks(component_type) {
+            if let Some(previous) = &previous {
+                self.hooks
+                    .run_on_remove(component_type, entity, previous, &mut self.commands);
+            }
 
-    pub fn remove_component(&mut self, entity: Entity, component_type: TypeId) {
-        if !self.components.contains_key(&component_type) {
-            return;
+            let component = storage.get(entity).unwrap();
+            self.hooks
+                .run_on_add(component_type, entity, component, &mut self.commands);
         }
 
-        let components = self.components.get_mut(&component_type).unwrap();
-        if components.len() <= entity.id() as usize {
-            return;
+        previous
+    }
+
+    pub(crate) fn storages(&self) -> impl Iterator<Item = (TypeId, &dyn AnyStorage)> {
+        self.components
+            .iter()
+            .map(|(component_type, storage)| (*component_type, storage.as_ref()))
+    }
+
+    pub(crate) fn reserve_components<T: Component>(&mut self, additional: usize) {
+        self.components
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Box::new(SparseSet::<T>::new()))
+            .as_any_mut()
+            .downcast_mut::<SparseSet<T>>()
+            .unwrap()
+            .reserve(additional);
+    }
+
+    /// Inserts a component that was moved from another world, without running
+    /// any hooks.
+    pub(crate) fn insert_moved_component<T: Component>(&mut self, entity: Entity, component: T) {
+        let tick = self.change_tick;
+
+        self.names.insert(entity, &component);
+        self.components
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Box::new(SparseSet::<T>::new()))
+            .as_any_mut()
+            .downcast_mut::<SparseSet<T>>()
+            .unwrap()
+            .insert(entity, component, tick);
+    }
+
+    pub(crate) fn remove_component_by_type(&mut self, entity: Entity, component_type: TypeId) {
+        let hooks = &mut self.hooks;
+        let names = &mut self.names;
+        let commands = &mut self.commands;
+        let was_removed = self
+            .components
+            .get_mut(&component_type)
+            .is_some_and(|storage| {
+                storage.remove(entity, &mut |component| {
+                    names.remove(entity, component);
+                    hooks.run_on_remove(component_type, entity, component, commands)
+                })
+            });
+
+        if was_removed {
+            self.record_removal(component_type, entity);
         }
+    }
 
-        components[entity.id() as usize] = None;
+    pub(crate) fn record_removal(&mut self, component_type: TypeId, entity: Entity) {
+        self.removed.entry(component_type).or_default().push(entity);
     }
 }
 
@@ -97,6 +587,7 @@ impl World {
 mod tests {
     use super::*;
 
+    use crate::ecs::{Added, Changed, With, Without};
     use crate::Vector2f;
 
     #[derive(Debug)]
@@ -106,6 +597,16 @@ mod tests {
 
     impl Component for Transform {}
 
+    #[derive(Debug, PartialEq)]
+    struct Velocity(Vector2f);
+
+    impl Component for Velocity {}
+
+    #[derive(Debug, PartialEq)]
+    struct Frozen;
+
+    impl Component for Frozen {}
+
     #[test]
     fn get_component_works() {
         let mut world = World::new();
@@ -124,4 +625,435 @@ mod tests {
             Some(Vector2f::new(10.0, 10.0))
         );
     }
+
+    #[test]
+    fn kill_entity_removes_the_entity() {
+        let mut world = World::new();
+
+        let entity = world
+            .create_entity()
+            .with(Transform {
+                position: Vector2f::ZERO,
+            })
+            .build();
+
+        assert_eq!(world.kill_entity(entity), Ok(()));
+        assert!(world.entities.is_empty());
+        assert!(!world.allocator.is_alive(entity));
+    }
+
+    #[test]
+    fn spawn_batch_and_kill_entities_work() {
+        let mut world = World::new();
+
+        let spawned = world.spawn_batch((0..5).map(|i| {
+            (
+                Transform {
+                    position: Vector2f::new(i as f32, 0.0),
+                },
+                Velocity(Vector2f::UNIT_X),
+            )
+        }));
+        assert_eq!(world.entities(), spawned.as_slice());
+        assert_eq!(world.storage::<Velocity>().unwrap().len(), 5);
+        assert_eq!(
+            world
+                .get_component::<Transform>(spawned[3])
+                .unwrap()
+                .position,
+            Vector2f::new(3.0, 0.0)
+        );
+
+        assert_eq!(world.kill_entities([spawned[0], spawned[2]]), Ok(()));
+        let mut remaining = world.entities().to_vec();
+        remaining.sort_by_key(|entity| entity.id());
+        assert_eq!(remaining, vec![spawned[1], spawned[3], spawned[4]]);
+        assert!(!world.is_alive(spawned[0]));
+        assert!(!world.has_component::<Velocity>(spawned[2]));
+
+        assert_eq!(
+            world.kill_entities([spawned[1], spawned[1], spawned[3]]),
+            Err(EcsError::DeadEntity(spawned[1]))
+        );
+        assert!(!world.is_alive(spawned[1]));
+        assert!(world.is_alive(spawned[3]));
+        assert_eq!(world.entities().len(), 2);
+    }
+
+    #[test]
+    fn kill_entity_fails_for_dead_entities() {
+        let mut world = World::new();
+
+        let entity = world.create_entity().build();
+
+        assert_eq!(world.kill_entity(entity), Ok(()));
+        assert_eq!(world.kill_entity(entity), Err(EcsError::DeadEntity(entity)));
+    }
+
+    #[test]
+    fn kill_entity_fails_for_stale_entities() {
+        let mut world = World::new();
+
+        let stale = world.create_entity().build();
+        world.kill_entity(stale).unwrap();
+
+        let recycled = world.create_entity().build();
+
+        assert_eq!(stale.id(), recycled.id());
+        assert_eq!(world.kill_entity(stale), Err(EcsError::DeadEntity(stale)));
+        assert!(world.allocator.is_alive(recycled));
+    }
+
+    #[test]
+    fn apply_commands_works() {
+        let mut world = World::new();
+
+        let doomed = world.create_entity().build();
+
+        world.commands.despawn(doomed);
+        world
+            .commands
+            .spawn()
+            .with(Transform {
+                position: Vector2f::new(5.0, 5.0),
+            })
+            .build();
+
+        assert_eq!(world.entities, vec![doomed]);
+
+        assert_eq!(world.apply_commands(), Ok(()));
+        assert!(world.commands.is_empty());
+        assert_eq!(world.entities.len(), 1);
+
+        let spawned = world.entities[0];
+        assert_eq!(
+            world
+                .get_component::<Transform>(spawned)
+                .map(|transform| transform.position),
+            Some(Vector2f::new(5.0, 5.0))
+        );
+    }
+
+    #[test]
+    fn apply_commands_fails_on_double_despawn() {
+        let mut world = World::new();
+
+        let entity = world.create_entity().build();
+
+        world.commands.despawn(entity);
+        world.commands.despawn(entity);
+
+        assert_eq!(world.apply_commands(), Err(EcsError::DeadEntity(entity)));
+        assert!(world.commands.is_empty());
+    }
+
+    #[test]
+    fn query_works() {
+        let mut world = World::new();
+
+        let moving = world
+            .create_entity()
+            .with(Transform {
+                position: Vector2f::ZERO,
+            })
+            .with(Velocity(Vector2f::UNIT_X))
+            .build();
+        let frozen = world
+            .create_entity()
+            .with(Transform {
+                position: Vector2f::ZERO,
+            })
+            .with(Velocity(Vector2f::UNIT_Y))
+            .with(Frozen)
+            .build();
+        world
+            .create_entity()
+            .with(Transform {
+                position: Vector2f::ZERO,
+            })
+            .build();
+
+        for (_, (transform, velocity)) in world
+            .query_filtered::<(&mut Transform, &Velocity), Without<Frozen>>()
+            .unwrap()
+        {
+            transform.position += velocity.0;
+        }
+
+        assert_eq!(
+            world
+                .get_component::<Transform>(moving)
+                .map(|transform| transform.position),
+            Some(Vector2f::UNIT_X)
+        );
+        assert_eq!(
+            world
+                .get_component::<Transform>(frozen)
+                .map(|transform| transform.position),
+            Some(Vector2f::ZERO)
+        );
+
+        let velocities = world
+            .query::<(&Transform, Option<&Velocity>)>()
+            .unwrap()
+            .map(|(_, (_, velocity))| velocity.map(|velocity| velocity.0))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            velocities,
+            vec![Some(Vector2f::UNIT_X), Some(Vector2f::UNIT_Y), None]
+        );
+
+        let frozen_entities = world
+            .query_filtered::<(), With<Frozen>>()
+            .unwrap()
+            .map(|(entity, _)| entity)
+            .collect::<Vec<_>>();
+
+        assert_eq!(frozen_entities, vec![frozen]);
+    }
+
+    #[test]
+    fn query_rejects_aliased_mutable_access() {
+        let mut world = World::new();
+
+        assert!(matches!(
+            world.query::<(&mut Transform, &mut Transform)>(),
+            Err(EcsError::ConflictingAccess(_))
+        ));
+        assert!(matches!(
+            world.query::<(&Transform, Option<&mut Transform>)>(),
+            Err(EcsError::ConflictingAccess(_))
+        ));
+        assert!(world.query::<(&Transform, &Transform)>().is_ok());
+    }
+
+    #[test]
+    fn mutable_query_items_can_be_held_together() {
+        let mut world = World::new();
+
+        let entities = (0..3)
+            .map(|_| world.create_entity().with(Velocity(Vector2f::ZERO)).build())
+            .collect::<Vec<_>>();
+        world.clear_trackers();
+
+        let mut velocities = world
+            .query::<&mut Velocity>()
+            .unwrap()
+            .map(|(_, velocity)| velocity)
+            .collect::<Vec<_>>();
+        for velocity in velocities.iter_mut() {
+            velocity.0 += Vector2f::UNIT_X;
+        }
+
+        let changed = world
+            .query_filtered::<&Velocity, Changed<Velocity>>()
+            .unwrap()
+            .map(|(entity, velocity)| (entity, velocity.0))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            changed,
+            entities
+                .into_iter()
+                .map(|entity| (entity, Vector2f::UNIT_X))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct Score(u32);
+
+    #[test]
+    fn resources_work() {
+        let mut world = World::new();
+
+        assert_eq!(
+            world.resource::<Score>(),
+            Err(EcsError::MissingResource(std::any::type_name::<Score>()))
+        );
+
+        assert_eq!(world.insert_resource(Score(0)), None);
+
+        world.resource_mut::<Score>().unwrap().0 += 10;
+        assert_eq!(world.resource::<Score>(), Ok(&Score(10)));
+
+        assert_eq!(world.insert_resource(Score(20)), Some(Score(10)));
+        assert_eq!(world.remove_resource::<Score>(), Some(Score(20)));
+        assert!(!world.has_resource::<Score>());
+    }
+
+    #[test]
+    fn resource_scope_works() {
+        let mut world = World::new();
+
+        world.insert_resource(Score(0));
+        world
+            .create_entity()
+            .with(Velocity(Vector2f::UNIT_X))
+            .build();
+        world
+            .create_entity()
+            .with(Velocity(Vector2f::UNIT_Y))
+            .build();
+
+        world
+            .resource_scope(|world, score: &mut Score| {
+                score.0 += world.query::<&Velocity>().unwrap().count() as u32;
+            })
+            .unwrap();
+
+        assert_eq!(world.resource::<Score>(), Ok(&Score(2)));
+    }
+
+    #[test]
+    fn insert_and_remove_component_work() {
+        let mut world = World::new();
+
+        let entity = world.create_entity().build();
+
+        assert_eq!(world.insert_component(entity, Frozen), Ok(None));
+        assert!(world.has_component::<Frozen>(entity));
+
+        assert_eq!(
+            world.insert_component(entity, Velocity(Vector2f::UNIT_X)),
+            Ok(None)
+        );
+        assert_eq!(
+            world.insert_component(entity, Velocity(Vector2f::UNIT_Y)),
+            Ok(Some(Velocity(Vector2f::UNIT_X)))
+        );
+
+        assert_eq!(
+            world.remove_component::<Velocity>(entity),
+            Some(Velocity(Vector2f::UNIT_Y))
+        );
+        assert_eq!(world.remove_component::<Velocity>(entity), None);
+        assert_eq!(
+            world.take_component::<Velocity>(entity),
+            Err(EcsError::MissingComponent {
+                entity,
+                component: std::any::type_name::<Velocity>()
+            })
+        );
+    }
+
+    #[test]
+    fn insert_and_remove_component_fail_for_dead_entities() {
+        let mut world = World::new();
+
+        let entity = world.create_entity().with(Frozen).build();
+        world.kill_entity(entity).unwrap();
+
+        assert_eq!(
+            world.insert_component(entity, Frozen),
+            Err(EcsError::DeadEntity(entity))
+        );
+        assert_eq!(
+            world.take_component::<Frozen>(entity),
+            Err(EcsError::DeadEntity(entity))
+        );
+        assert_eq!(world.remove_component::<Frozen>(entity), None);
+    }
+
+    #[test]
+    fn stale_entities_cannot_read_components() {
+        let mut world = World::new();
+
+        let stale = world.create_entity().with(Frozen).build();
+        world.kill_entity(stale).unwrap();
+
+        let recycled = world.create_entity().with(Frozen).build();
+
+        assert_eq!(stale.id(), recycled.id());
+        assert!(!world.is_alive(stale));
+        assert!(!world.has_component::<Frozen>(stale));
+        assert_eq!(world.get_component_mut::<Frozen>(stale), None);
+        assert!(world.has_component::<Frozen>(recycled));
+    }
+
+    #[test]
+    fn unknown_entities_cannot_read_components() {
+        let mut world = World::new();
+
+        world.create_entity().with(Frozen).build();
+
+        let unknown = Entity::from_bits((1 << 32) | 1000).unwrap();
+
+        assert!(!world.is_alive(unknown));
+        assert_eq!(world.get_component::<Frozen>(unknown), None);
+        assert_eq!(
+            world.kill_entity(unknown),
+            Err(EcsError::DeadEntity(unknown))
+        );
+    }
+
+    #[test]
+    fn added_and_changed_components_are_tracked_per_tick() {
+        let mut world = World::new();
+
+        let first = world.create_entity().with(Velocity(Vector2f::ZERO)).build();
+
+        world.clear_trackers();
+
+        let second = world.create_entity().with(Velocity(Vector2f::ZERO)).build();
+
+        let added = world
+            .query_filtered::<(), Added<Velocity>>()
+            .unwrap()
+            .map(|(entity, _)| entity)
+            .collect::<Vec<_>>();
+        assert_eq!(added, vec![second]);
+
+        world.clear_trackers();
+
+        world.get_component_mut::<Velocity>(first).unwrap().0 = Vector2f::UNIT_X;
+
+        let changed = world
+            .query_filtered::<(), Changed<Velocity>>()
+            .unwrap()
+            .map(|(entity, _)| entity)
+            .collect::<Vec<_>>();
+        assert_eq!(changed, vec![first]);
+
+        assert!(world.is_changed::<Velocity>(first));
+        assert!(!world.is_added::<Velocity>(first));
+        assert!(!world.is_changed::<Velocity>(second));
+    }
+
+    #[test]
+    fn mutable_queries_mark_components_as_changed() {
+        let mut world = World::new();
+
+        let entity = world.create_entity().with(Velocity(Vector2f::ZERO)).build();
+
+        world.clear_trackers();
+        assert!(!world.is_changed::<Velocity>(entity));
+
+        for (_, _velocity) in world.query::<&Velocity>().unwrap() {}
+        assert!(!world.is_changed::<Velocity>(entity));
+
+        for (_, _velocity) in world.query::<&mut Velocity>().unwrap() {}
+        assert!(world.is_changed::<Velocity>(entity));
+    }
+
+    #[test]
+    fn removed_components_are_tracked_per_tick() {
+        let mut world = World::new();
+
+        let taken = world.create_entity().with(Frozen).build();
+        let killed = world.create_entity().with(Frozen).build();
+
+        world.take_component::<Frozen>(taken).unwrap();
+        world.kill_entity(killed).unwrap();
+
+        assert_eq!(
+            world.removed::<Frozen>().collect::<Vec<_>>(),
+            vec![taken, killed]
+        );
+        assert_eq!(world.removed::<Velocity>().count(), 0);
+
+        world.clear_trackers();
+
+        assert_eq!(world.removed::<Frozen>().count(), 0);
+    }
 }
@@ -0,0 +1,414 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::ecs::{Children, Entity, EntityMap, MapEntities, Parent, World};
+use crate::error::{EcsError, SceneError};
+
+/// The name of the world that is active when the game starts.
+pub const MAIN_WORLD: &str = "main";
+
+/// The worlds that are not currently active, along with the name of the one
+/// that is.
+pub(crate) struct Worlds {
+    active: String,
+    inactive: HashMap<String, World>,
+}
+
+impl Worlds {
+    pub(crate) fn new() -> Self {
+        Self {
+            active: MAIN_WORLD.to_string(),
+            inactive: HashMap::new(),
+        }
+    }
+
+    /// Returns the name of the active world.
+    pub(crate) fn active(&self) -> &str {
+        &self.active
+    }
+
+    /// Creates a new, empty, inactive world with the given name, with the same
+    /// registrations as the active world.
+    pub(crate) fn create(&mut self, name: &str, active: &World) -> Result<&mut World, EcsError> {
+        if self.exists(name) {
+            return Err(EcsError::DuplicateWorld(name.to_string()));
+        }
+
+        Ok(self
+            .inactive
+            .entry(name.to_string())
+            .or_insert_with(|| active.new_sibling()))
+    }
+
+    /// Returns the world with the given name, which is `active` if it is the
+    /// active world.
+    pub(crate) fn get_mut<'a>(
+        &'a mut self,
+        name: &str,
+        active: &'a mut World,
+    ) -> Option<&'a mut World> {
+        if name == self.active {
+            Some(active)
+        } else {
+            self.inactive.get_mut(name)
+        }
+    }
+
+    /// Makes the world with the given name the active world, swapping it with
+    /// the world that is currently active.
+    pub(crate) fn activate(&mut self, name: &str, active: &mut World) -> Result<(), EcsError> {
+        if name == self.active {
+            return Ok(());
+        }
+
+        let mut world = self
+            .inactive
+            .remove(name)
+            .ok_or_else(|| EcsError::UnknownWorld(name.to_string()))?;
+
+        std::mem::swap(active, &mut world);
+
+        let previous = std::mem::replace(&mut self.active, name.to_string());
+        self.inactive.insert(previous, world);
+
+        Ok(())
+    }
+
+    /// Removes the inactive world with the given name and returns it.
+    pub(crate) fn remove(&mut self, name: &str) -> Result<World, EcsError> {
+        if name == self.active {
+            return Err(EcsError::ActiveWorld(name.to_string()));
+        }
+
+        self.inactive
+            .remove(name)
+            .ok_or_else(|| EcsError::UnknownWorld(name.to_string()))
+    }
+
+    /// Moves the given entities from one named world to another.
+    pub(crate) fn move_entities(
+        &mut self,
+        from: &str,
+        to: &str,
+        entities: &[Entity],
+        active: &mut World,
+    ) -> Result<EntityMap, SceneError> {
+        if from == to {
+            return Err(EcsError::SameWorld(from.to_string()).into());
+        }
+
+        for name in [from, to] {
+            if !self.exists(name) {
+                return Err(EcsError::UnknownWorld(name.to_string()).into());
+            }
+        }
+
+        // Take the source out of the map so that both worlds can be borrowed at once.
+        let mut source = match self.inactive.remove(from) {
+            Some(world) => world,
+            None => std::mem::take(active),
+        };
+
+        let result = match self.inactive.get_mut(to) {
+            Some(destination) => source.move_entities(destination, entities),
+            None => source.move_entities(active, entities),
+        };
+
+        if from == self.active {
+            *active = source;
+        } else {
+            self.inactive.insert(from.to_string(), source);
+        }
+
+        result
+    }
+
+    fn exists(&self, name: &str) -> bool {
+        name == self.active || self.inactive.contains_key(name)
+    }
+}
+
+impl World {
+    /// Moves the given entities, along with all of their components, into
+    /// another world, returning the mapping from the moved entities to the
+    /// entities that replace them.
+    ///
+    /// References to moved entities in [`Parent`], [`Children`] and the
+    /// components registered with [`World::register_component_with_entities`]
+    /// are remapped. Entities that are left behind are detached from the
+    /// moved entities in the hierarchy. Hooks are not run, since the
+    /// components are neither created nor destroyed.
+    ///
+    /// Returns an error if any of the entities is not alive, or if a registered
+    /// component refers to an entity that is left behind. In either case,
+    /// nothing is moved.
+    pub fn move_entities(
+        &mut self,
+        to: &mut World,
+        entities: &[Entity],
+    ) -> Result<EntityMap, SceneError> {
+        if let Some(&dead) = entities.iter().find(|&&entity| !self.is_alive(entity)) {
+            return Err(EcsError::DeadEntity(dead).into());
+        }
+
+        let mut moving = HashSet::with_capacity(entities.len());
+        let entities = entities
+            .iter()
+            .copied()
+            .filter(|&entity| moving.insert(entity))
+            .collect::<Vec<_>>();
+
+        // Check every reference before changing anything, so that a failure
+        // leaves both worlds as they were.
+        let mut staying_put = EntityMap::default();
+        for &entity in &entities {
+            staying_put.insert(entity, entity);
+        }
+
+        let mappers = self.registry.mappers().collect::<Vec<_>>();
+        for &entity in &entities {
+            for mapper in &mappers {
+                (mapper.check)(self, entity, &staying_put)?;
+            }
+        }
+
+        // The hierarchy is the only thing that can refer to the entities that
+        // are left behind, so cut those links.
+        for &entity in &entities {
+            if self
+                .parent(entity)
+                .is_some_and(|parent| !moving.contains(&parent))
+            {
+                self.remove_parent(entity)?;
+            }
+
+            let staying = self
+                .children(entity)
+                .iter()
+                .copied()
+                .filter(|child| !moving.contains(child))
+                .collect::<Vec<_>>();
+            for child in staying {
+                self.remove_parent(child)?;
+            }
+        }
+
+        let mut map = EntityMap::default();
+        for &entity in &entities {
+            let new_entity = to.spawn(std::iter::empty());
+            map.insert(entity, new_entity);
+
+            let names = &mut self.names;
+            let mut moved = Vec::new();
+            for (&component_type, storage) in self.components.iter_mut() {
+                if let Some(component) = storage.get_any(entity) {
+                    names.remove(entity, component);
+                }
+
+                if storage.move_to(entity, to, new_entity) {
+                    moved.push(component_type);
+                }
+            }
+
+            for component_type in moved {
+                self.record_removal(component_type, entity);
+            }
+
+            self.remove_from_entities(entity);
+            self.spatial_index.remove(entity);
+            self.allocator
+                .kill(entity)
+                .expect("moved entities were checked to be alive");
+        }
+
+        // Every reference was checked to be to a moved entity, and the links to
+        // the rest of the hierarchy were cut, so none of these can fail.
+        for (_, new_entity) in map.iter() {
+            if let Some(parent) = to.get_component_mut::<Parent>(new_entity) {
+                parent
+                    .map_entities(&map)
+                    .expect("parents were checked to be moved");
+            }
+
+            if let Some(children) = to.get_component_mut::<Children>(new_entity) {
+                children
+                    .map_entities(&map)
+                    .expect("children were checked to be moved");
+            }
+
+            for mapper in &mappers {
+                (mapper.map)(to, new_entity, &map).expect("references were checked to be moved");
+            }
+        }
+
+        Ok(map)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::{Arc, Mutex};
+
+    use crate::ecs::test_util::{registered_world, Health, Target};
+    use crate::ecs::{Changed, Name};
+
+    #[test]
+    fn move_entities_remaps_references() {
+        let mut level = registered_world();
+
+        let root = level.create_entity().with(Name::new("root")).build();
+        let door = level.create_entity().build();
+        let switch = level.create_entity().with(Target(door)).build();
+        let left_behind = level.create_entity().build();
+        level.set_parent(door, root).unwrap();
+        level.set_parent(switch, root).unwrap();
+        level.set_parent(left_behind, root).unwrap();
+
+        let mut active = World::new();
+        active.create_entity().build();
+
+        let map = level
+            .move_entities(&mut active, &[root, door, switch])
+            .unwrap();
+        let (root, door, switch) = (
+            map.get(root).unwrap(),
+            map.get(door).unwrap(),
+            map.get(switch).unwrap(),
+        );
+
+        assert_eq!(level.entities(), &[left_behind]);
+        assert_eq!(level.parent(left_behind), None);
+        assert_eq!(level.find_by_name("root"), None);
+
+        assert_eq!(active.entities().len(), 4);
+        assert_eq!(active.find_by_name("root"), Some(root));
+        assert_eq!(active.children(root), &[door, switch]);
+        assert_eq!(active.parent(switch), Some(root));
+        assert_eq!(active.get_component(switch), Some(&Target(door)));
+    }
+
+    #[test]
+    fn move_entities_fails_for_dead_entities() {
+        let mut from = World::new();
+        let mut to = World::new();
+
+        let alive = from.create_entity().build();
+        let dead = from.create_entity().build();
+        from.kill_entity(dead).unwrap();
+
+        assert!(matches!(
+            from.move_entities(&mut to, &[alive, dead]),
+            Err(SceneError::Ecs(EcsError::DeadEntity(entity))) if entity == dead
+        ));
+        assert!(from.is_alive(alive));
+        assert!(to.entities().is_empty());
+    }
+
+    #[test]
+    fn move_entities_fails_for_references_to_entities_left_behind() {
+        let mut from = registered_world();
+        let mut to = World::new();
+
+        let root = from.create_entity().build();
+        let door = from.create_entity().build();
+        let switch = from.create_entity().with(Target(door)).build();
+        from.set_parent(switch, root).unwrap();
+        from.clear_trackers();
+
+        assert!(matches!(
+            from.move_entities(&mut to, &[root, switch]),
+            Err(SceneError::UnmappedEntity(entity)) if entity == door
+        ));
+
+        assert_eq!(from.entities(), &[root, door, switch]);
+        assert_eq!(from.parent(switch), Some(root));
+        assert_eq!(from.get_component(switch), Some(&Target(door)));
+        assert!(from
+            .query_filtered::<&Target, Changed<Target>>()
+            .unwrap()
+            .next()
+            .is_none());
+        assert!(to.entities().is_empty());
+    }
+
+    #[test]
+    fn worlds_can_be_created_activated_and_dropped() {
+        let mut worlds = Worlds::new();
+        let mut active = World::new();
+        let player = active.create_entity().build();
+
+        let menu = worlds.create("menu", &active).unwrap();
+        let button = menu.create_entity().with(Name::new("start")).build();
+        assert!(matches!(
+            worlds.create("menu", &active),
+            Err(EcsError::DuplicateWorld(_))
+        ));
+
+        worlds.activate("menu", &mut active).unwrap();
+        assert_eq!(worlds.active(), "menu");
+        assert_eq!(active.find_by_name("start"), Some(button));
+
+        let map = worlds
+            .move_entities(MAIN_WORLD, "menu", &[player], &mut active)
+            .unwrap();
+        assert!(active.is_alive(map.get(player).unwrap()));
+        assert!(worlds
+            .get_mut(MAIN_WORLD, &mut active)
+            .unwrap()
+            .entities()
+            .is_empty());
+
+        assert_eq!(
+            worlds.remove("menu").err(),
+            Some(EcsError::ActiveWorld("menu".to_string()))
+        );
+        worlds.activate(MAIN_WORLD, &mut active).unwrap();
+        assert!(worlds.remove("menu").is_ok());
+        assert_eq!(
+            worlds.activate("menu", &mut active),
+            Err(EcsError::UnknownWorld("menu".to_string()))
+        );
+    }
+
+    #[test]
+    fn new_worlds_start_with_the_registrations_of_the_active_world() {
+        let mut worlds = Worlds::new();
+        let mut active = World::new();
+        let added = Arc::new(Mutex::new(Vec::new()));
+
+        active.register_component::<Health>("Health");
+        active.register_debug::<Health>();
+        active.register_snapshot::<Health>();
+        active.set_spatial_cell_size(32.0).unwrap();
+        active
+            .create_prefab("orc")
+            .with(Health(20))
+            .build()
+            .unwrap();
+        active.on_add::<Health>({
+            let added = added.clone();
+            move |_, health, _| added.lock().unwrap().push(health.0)
+        });
+
+        let level = worlds.create("level", &active).unwrap();
+        let orc = level.spawn_prefab("orc").build().unwrap();
+
+        assert_eq!(level.get_component(orc), Some(&Health(20)));
+        assert_eq!(*added.lock().unwrap(), vec![20]);
+        assert_eq!(
+            level.dump().entities[0].components[0].value.as_deref(),
+            Some("Health(20)")
+        );
+        assert_eq!(level.spatial_index.cell_size(), 32.0);
+
+        let snapshot = level.snapshot();
+        *level.get_component_mut::<Health>(orc).unwrap() = Health(5);
+        assert!(level.snapshot() != snapshot);
+
+        // Registrations made afterwards only apply to the world they're made on.
+        level.register_component::<Target>("Target");
+        assert!(level.is_registered_component("Target"));
+        assert!(!active.is_registered_component("Target"));
+    }
+}
@@ -1,6 +1,9 @@
 use sdl2::ttf::FontError;
 use thiserror::Error;
 
+use crate::ecs::{Entity, Stage};
+use crate::timer::{Interval, TimerHandle};
+
 pub(crate) use anyhow::Context as AnyhowContext;
 pub use anyhow::Result;
 
@@ -15,3 +18,207 @@ pub enum Sdl2Error {
     #[error("Encountered an SDL2 error: {0}")]
     ErrorMessage(String),
 }
+
+/// An error originating from the entity component system.
+#[derive(Debug, Error, PartialEq)]
+pub enum EcsError {
+    /// The entity has already been deleted, or the handle refers to a previous
+    /// generation of the entity.
+    #[error("Entity {0:?} is not alive")]
+    DeadEntity(Entity),
+
+    /// An entity does not have a component of the requested type.
+    #[error("Entity {entity:?} does not have a component of type `{component}`")]
+    MissingComponent {
+        /// The entity that was missing the component.
+        entity: Entity,
+
+        /// The name of the component type.
+        component: &'static str,
+    },
+
+    /// An entity cannot be made a child of itself or of one of its descendants.
+    #[error("Entity {parent:?} cannot be the parent of entity {child:?}")]
+    InvalidParent {
+        /// The entity that was to be made a child.
+        child: Entity,
+
+        /// The entity that was to be made the parent.
+        parent: Entity,
+    },
+
+    /// A query accesses a component type mutably more than once, or both
+    /// mutably and immutably.
+    #[error("Query has conflicting access to component `{0}`")]
+    ConflictingAccess(&'static str),
+
+    /// A system accessed a component or resource type without declaring it.
+    #[error("System `{system}` accessed `{type_name}` without declaring it")]
+    UndeclaredAccess {
+        /// The name of the system.
+        system: &'static str,
+
+        /// The name of the undeclared component or resource type.
+        type_name: &'static str,
+    },
+
+    /// A resource was requested that does not exist in the world.
+    #[error("Resource `{0}` does not exist in the world")]
+    MissingResource(&'static str),
+
+    /// More than one system in a stage has the same name.
+    #[error("More than one system is named `{0}`")]
+    DuplicateSystem(&'static str),
+
+    /// A system is ordered relative to a system that does not exist in the same stage.
+    #[error("System `{system}` is ordered relative to unknown system `{other}`")]
+    UnknownSystem {
+        /// The name of the system with the ordering constraint.
+        system: &'static str,
+
+        /// The name of the unknown system.
+        other: &'static str,
+    },
+
+    /// An exclusive system was added while the context was being built, where
+    /// it cannot be kept because it takes a context without the game context.
+    #[error("Exclusive system `{0}` was added while building the context; add it after the context is built")]
+    SetupSystem(&'static str),
+
+    /// The ordering constraints between the systems in a stage form a cycle.
+    #[error("Systems in the {stage:?} stage have cyclic ordering constraints: {cycle}")]
+    SystemCycle {
+        /// The stage containing the cycle.
+        stage: Stage,
+
+        /// The names of the systems in the cycle.
+        cycle: String,
+    },
+
+    /// The size of the cells in the spatial index is not a positive, finite number.
+    #[error("Spatial index cell size must be a positive number, but was {0}")]
+    InvalidCellSize(f32),
+
+    /// A world with the same name already exists.
+    #[error("A world named `{0}` already exists")]
+    DuplicateWorld(String),
+
+    /// No world exists with the given name.
+    #[error("No world is named `{0}`")]
+    UnknownWorld(String),
+
+    /// The active world cannot be dropped.
+    #[error("World `{0}` cannot be dropped while it is active")]
+    ActiveWorld(String),
+
+    /// Entities cannot be moved from a world to itself.
+    #[error("Entities cannot be moved from world `{0}` to itself")]
+    SameWorld(String),
+}
+
+/// An error encountered while saving or loading a scene.
+#[derive(Debug, Error)]
+pub enum SceneError {
+    /// The scene contains a component that has not been registered.
+    #[error("Scene contains unknown component `{0}`")]
+    UnknownComponent(String),
+
+    /// A component type was used in a scene or prefab without being registered.
+    #[error("Component type `{0}` has not been registered")]
+    UnregisteredComponent(&'static str),
+
+    /// The scene contains more than one entity with the same ID.
+    #[error("Scene contains entity {0:?} more than once")]
+    DuplicateEntity(Entity),
+
+    /// A component refers to an entity that is not part of the scene.
+    #[error("Scene refers to entity {0:?}, which is not part of the scene")]
+    UnmappedEntity(Entity),
+
+    /// A component could not be serialized or deserialized.
+    #[error("Component `{component}` is invalid: {source}")]
+    InvalidComponent {
+        /// The registered name of the component.
+        component: String,
+
+        /// The underlying error.
+        source: serde_json::Error,
+    },
+
+    /// The scene is not valid JSON, or does not have the expected structure.
+    #[error("Scene is malformed: {0}")]
+    Malformed(#[from] serde_json::Error),
+
+    /// The scene could not be applied to the world.
+    #[error(transparent)]
+    Ecs(#[from] EcsError),
+}
+
+/// An error encountered while defining, loading or spawning a prefab.
+#[derive(Debug, Error)]
+pub enum PrefabError {
+    /// A prefab was requested that has not been defined.
+    #[error("Prefab `{0}` does not exist")]
+    UnknownPrefab(String),
+
+    /// A prefab inherits from itself, either directly or through its bases.
+    #[error("Prefabs have cyclic inheritance: {0}")]
+    InheritanceCycle(String),
+
+    /// A prefab file could not be read.
+    #[error("Failed to read prefab file: {filename}")]
+    Io {
+        /// The name of the prefab file.
+        filename: String,
+
+        /// The underlying error.
+        source: std::io::Error,
+    },
+
+    /// A prefab file is not valid JSON, or does not have the expected structure.
+    #[error("Prefab file {filename} is malformed: {source}")]
+    Malformed {
+        /// The name of the prefab file.
+        filename: String,
+
+        /// The underlying error.
+        source: serde_json::Error,
+    },
+
+    /// The components of a prefab could not be serialized or deserialized.
+    #[error(transparent)]
+    Scene(#[from] SceneError),
+}
+
+/// An error encountered while starting a timer.
+#[derive(Debug, Error, PartialEq)]
+pub enum TimerError {
+    /// A timer with a callback was started while the context was being built,
+    /// where the callback would not have access to the game context.
+    #[error("Timer {0:?} with a callback was started while building the context; start it after the context is built")]
+    SetupCallback(TimerHandle),
+
+    /// The timer's interval is a negative, infinite or NaN number of seconds.
+    #[error("Timer interval must be a finite, non-negative number of seconds, but was {0:?}")]
+    InvalidInterval(Interval),
+}
+
+/// An error encountered while configuring the game loop's clock.
+#[derive(Debug, Error, PartialEq)]
+pub enum TimeError {
+    /// The tick rate is not a positive, finite number of seconds.
+    #[error("Tick rate must be a positive number of seconds, but was {0}")]
+    InvalidTickRate(f64),
+
+    /// The target frame rate is not a positive, finite number of frames per second.
+    #[error("Target frame rate must be a positive number of frames per second, but was {0}")]
+    InvalidFrameRate(f64),
+
+    /// The time scale is negative or not finite.
+    #[error("Time scale must be a finite number of at least 0, but was {0}")]
+    InvalidTimeScale(f64),
+
+    /// The maximum number of updates per frame is zero, which would stop the game.
+    #[error("Maximum updates per frame must be at least 1")]
+    NoUpdatesPerFrame,
+}
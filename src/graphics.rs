@@ -1,4 +1,5 @@
 mod animation;
+mod canvas;
 mod color;
 mod context;
 mod font;
@@ -8,6 +9,7 @@ mod text;
 mod view;
 
 pub use self::animation::*;
+pub(crate) use self::canvas::*;
 pub use self::color::*;
 pub(crate) use self::context::*;
 pub use self::font::*;
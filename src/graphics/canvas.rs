@@ -0,0 +1,122 @@
+use sdl2::image::LoadTexture;
+use sdl2::pixels::PixelFormatEnum;
+use sdl2::rect::Rect as SdlRect;
+use sdl2::render::{Canvas as SdlCanvas, Texture as SdlTexture, TextureValueError};
+use sdl2::surface::Surface;
+use sdl2::video::Window;
+
+use crate::graphics::Color;
+
+/// Runs the same expression against whichever kind of SDL canvas is in use.
+macro_rules! with_canvas {
+    ($canvas:expr, $inner:ident => $body:expr) => {
+        match $canvas {
+            Canvas::Window($inner) => $body,
+            Canvas::Headless($inner) => $body,
+        }
+    };
+}
+
+/// The render target of a [`Context`](crate::Context).
+pub(crate) enum Canvas {
+    /// Renders to a window on the screen.
+    Window(SdlCanvas<Window>),
+
+    /// Renders to an offscreen surface, using the software renderer.
+    Headless(SdlCanvas<Surface<'static>>),
+}
+
+impl Canvas {
+    /// Returns a canvas that renders to an offscreen surface of the given size.
+    pub(crate) fn headless(width: u32, height: u32) -> Result<Self, String> {
+        let surface = Surface::new(width, height, PixelFormatEnum::RGBA8888)?;
+
+        Ok(Canvas::Headless(surface.into_canvas()?))
+    }
+
+    /// Returns the window being rendered to, or `None` if the canvas is headless.
+    pub(crate) fn window(&self) -> Option<&Window> {
+        match self {
+            Canvas::Window(canvas) => Some(canvas.window()),
+            Canvas::Headless(_) => None,
+        }
+    }
+
+    /// Returns the window being rendered to, or `None` if the canvas is headless.
+    pub(crate) fn window_mut(&mut self) -> Option<&mut Window> {
+        match self {
+            Canvas::Window(canvas) => Some(canvas.window_mut()),
+            Canvas::Headless(_) => None,
+        }
+    }
+
+    /// Returns the size of the window, or of the offscreen surface if the
+    /// canvas is headless.
+    pub(crate) fn size(&self) -> (u32, u32) {
+        match self {
+            Canvas::Window(canvas) => canvas.window().size(),
+            Canvas::Headless(canvas) => canvas.surface().size(),
+        }
+    }
+
+    pub(crate) fn set_draw_color(&mut self, color: Color) {
+        with_canvas!(self, canvas => canvas.set_draw_color(color))
+    }
+
+    pub(crate) fn clear(&mut self) {
+        with_canvas!(self, canvas => canvas.clear())
+    }
+
+    pub(crate) fn present(&mut self) {
+        with_canvas!(self, canvas => canvas.present())
+    }
+
+    pub(crate) fn set_viewport(&mut self, rect: SdlRect) {
+        with_canvas!(self, canvas => canvas.set_viewport(rect))
+    }
+
+    pub(crate) fn set_scale(&mut self, scale_x: f32, scale_y: f32) -> Result<(), String> {
+        with_canvas!(self, canvas => canvas.set_scale(scale_x, scale_y))
+    }
+
+    pub(crate) fn copy(
+        &mut self,
+        texture: &SdlTexture,
+        src: Option<SdlRect>,
+        dst: SdlRect,
+    ) -> Result<(), String> {
+        with_canvas!(self, canvas => canvas.copy(texture, src, dst))
+    }
+
+    pub(crate) fn load_texture(&self, filename: &str) -> Result<SdlTexture, String> {
+        with_canvas!(self, canvas => canvas.texture_creator().load_texture(filename))
+    }
+
+    pub(crate) fn create_texture_from_surface(
+        &self,
+        surface: &Surface,
+    ) -> Result<SdlTexture, TextureValueError> {
+        with_canvas!(self, canvas => canvas.texture_creator().create_texture_from_surface(surface))
+    }
+
+    /// Creates a texture of the given size that is filled with a color.
+    pub(crate) fn create_filled_texture(
+        &mut self,
+        width: u32,
+        height: u32,
+        color: Color,
+    ) -> crate::Result<SdlTexture> {
+        with_canvas!(self, canvas => {
+            let mut texture = canvas
+                .texture_creator()
+                .create_texture_target(None, width, height)?;
+
+            canvas.with_texture_canvas(&mut texture, |texture_canvas| {
+                texture_canvas.set_draw_color(color);
+                texture_canvas.clear();
+            })?;
+
+            Ok(texture)
+        })
+    }
+}
@@ -1,4 +1,3 @@
-use sdl2::image::LoadTexture;
 use sdl2::rect::Rect as SdlRect;
 
 use crate::error::{AnyhowContext, Sdl2Error};
@@ -12,8 +11,8 @@ pub struct Image {
 
 impl Image {
     pub fn from_file<G>(ctx: &mut Context<G>, filename: &str) -> Result<Self> {
-        let texture_creator = ctx.canvas.texture_creator();
-        let texture = texture_creator
+        let texture = ctx
+            .canvas
             .load_texture(filename)
             .map_err(Sdl2Error::ErrorMessage)
             .with_context(|| format!("Failed to create image from file: {}", filename))?;
@@ -32,16 +31,9 @@ impl Image {
     pub fn from_color<G>(ctx: &mut Context<G>, size: Vector2u, color: Color) -> Result<Self> {
         const ERROR_CONTEXT: &'static str = "Failed to create image from color";
 
-        let texture_creator = ctx.canvas.texture_creator();
-        let mut texture = texture_creator
-            .create_texture_target(None, size.x, size.y)
-            .context(ERROR_CONTEXT)?;
-
-        ctx.canvas
-            .with_texture_canvas(&mut texture, |texture_canvas| {
-                texture_canvas.set_draw_color(color);
-                texture_canvas.clear();
-            })
+        let texture = ctx
+            .canvas
+            .create_filled_texture(size.x, size.y, color)
             .context(ERROR_CONTEXT)?;
 
         let texture_ref = AssetRef(ctx.graphics.counter);
@@ -14,15 +14,13 @@ pub struct Text {
 
 impl Text {
     pub fn new<G, S: Into<String>>(ctx: &mut Context<G>, string: S, font: &Font) -> Result<Self> {
-        let texture_creator = ctx.canvas.texture_creator();
-
         let string = string.into();
         let surface = font
             .font
             .render(&string)
             .blended(Color::WHITE)
             .map_err(Sdl2Error::FontError)?;
-        let texture = texture_creator.create_texture_from_surface(&surface)?;
+        let texture = ctx.canvas.create_texture_from_surface(&surface)?;
         let texture_query = texture.query();
 
         let texture_ref = AssetRef(ctx.graphics.counter);
@@ -18,20 +18,16 @@ pub mod error;
 pub mod graphics;
 pub mod input;
 pub mod time;
+pub mod timer;
 pub mod window;
 
 mod context;
 mod fps_tracker;
+mod state;
 mod vector2;
 
 pub use crate::context::*;
 pub use crate::error::Result;
 pub(crate) use crate::fps_tracker::*;
+pub use crate::state::*;
 pub use crate::vector2::*;
-
-pub trait State {
-    type Context;
-
-    fn update(&mut self, ctx: &mut Context<Self::Context>) -> Result<()>;
-    fn draw(&mut self, ctx: &mut Context<Self::Context>, dt: f64) -> Result<()>;
-}
@@ -0,0 +1,312 @@
+use crate::{Context, Result};
+
+/// A state of the game, such as a menu, a level or a pause screen.
+///
+/// States are kept on a stack. Only the state on top of the stack is updated
+/// and drawn, unless it lets the states below it through with
+/// [`State::update_below`] and [`State::draw_below`].
+pub trait State {
+    /// The type of the game context that the state runs in.
+    type Context;
+
+    /// Updates the state, returning the [`Transition`] to make afterwards.
+    ///
+    /// The time that the update advances the game by is returned by
+    /// [`time::delta`](crate::time::delta).
+    fn update(&mut self, ctx: &mut Context<Self::Context>) -> Result<Transition<Self::Context>>;
+
+    /// Draws the state.
+    fn draw(&mut self, ctx: &mut Context<Self::Context>, dt: f64) -> Result<()>;
+
+    /// Called when the state is added to the stack.
+    fn on_enter(&mut self, _ctx: &mut Context<Self::Context>) -> Result<()> {
+        Ok(())
+    }
+
+    /// Called when the state is removed from the stack.
+    fn on_exit(&mut self, _ctx: &mut Context<Self::Context>) -> Result<()> {
+        Ok(())
+    }
+
+    /// Called when another state is pushed on top of this one.
+    fn on_pause(&mut self, _ctx: &mut Context<Self::Context>) -> Result<()> {
+        Ok(())
+    }
+
+    /// Called when this state is on top of the stack again, after the state
+    /// above it was popped.
+    fn on_resume(&mut self, _ctx: &mut Context<Self::Context>) -> Result<()> {
+        Ok(())
+    }
+
+    /// Returns whether the state below this one should still be updated while
+    /// this state is on top of it.
+    ///
+    /// Only the transition returned by the state on top of the stack is made.
+    fn update_below(&self) -> bool {
+        false
+    }
+
+    /// Returns whether the state below this one should still be drawn, before
+    /// this state is drawn on top of it.
+    fn draw_below(&self) -> bool {
+        false
+    }
+}
+
+impl<S: State + ?Sized> State for &mut S {
+    type Context = S::Context;
+
+    fn update(&mut self, ctx: &mut Context<Self::Context>) -> Result<Transition<Self::Context>> {
+        (**self).update(ctx)
+    }
+
+    fn draw(&mut self, ctx: &mut Context<Self::Context>, dt: f64) -> Result<()> {
+        (**self).draw(ctx, dt)
+    }
+
+    fn on_enter(&mut self, ctx: &mut Context<Self::Context>) -> Result<()> {
+        (**self).on_enter(ctx)
+    }
+
+    fn on_exit(&mut self, ctx: &mut Context<Self::Context>) -> Result<()> {
+        (**self).on_exit(ctx)
+    }
+
+    fn on_pause(&mut self, ctx: &mut Context<Self::Context>) -> Result<()> {
+        (**self).on_pause(ctx)
+    }
+
+    fn on_resume(&mut self, ctx: &mut Context<Self::Context>) -> Result<()> {
+        (**self).on_resume(ctx)
+    }
+
+    fn update_below(&self) -> bool {
+        (**self).update_below()
+    }
+
+    fn draw_below(&self) -> bool {
+        (**self).draw_below()
+    }
+}
+
+/// A change to the stack of states, returned from [`State::update`].
+pub enum Transition<G> {
+    /// Keeps the current state.
+    None,
+
+    /// Pauses the current state and pushes a new state on top of it.
+    Push(Box<dyn State<Context = G>>),
+
+    /// Exits the current state and resumes the state below it. The game
+    /// quits if there is no state below it.
+    Pop,
+
+    /// Exits the current state and replaces it with a new state.
+    Switch(Box<dyn State<Context = G>>),
+
+    /// Exits all of the states and quits the game.
+    Quit,
+}
+
+impl<G> Transition<G> {
+    /// Returns a [`Transition::Push`] to the given state.
+    pub fn push<S: State<Context = G> + 'static>(state: S) -> Self {
+        Self::Push(Box::new(state))
+    }
+
+    /// Returns a [`Transition::Switch`] to the given state.
+    pub fn switch<S: State<Context = G> + 'static>(state: S) -> Self {
+        Self::Switch(Box::new(state))
+    }
+}
+
+/// Whether the root state of a [`StateStack`] is on the stack.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Root {
+    /// The root state has not been entered yet.
+    Waiting,
+    Entered,
+    Exited,
+}
+
+/// The stack of states that the game is running.
+///
+/// The state at the root of the stack is borrowed from the caller of
+/// [`Context::run`] or [`Context::step`], so it is passed in to each
+/// operation rather than being owned by the stack. The states that are pushed
+/// on top of it are kept between operations.
+pub(crate) struct StateStack<G> {
+    root: Root,
+    states: Vec<Box<dyn State<Context = G>>>,
+}
+
+impl<G> Default for StateStack<G> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<G> StateStack<G> {
+    pub(crate) fn new() -> Self {
+        Self {
+            root: Root::Waiting,
+            states: Vec::new(),
+        }
+    }
+
+    /// Returns whether the root state has been entered.
+    pub(crate) fn is_started(&self) -> bool {
+        self.root != Root::Waiting
+    }
+
+    /// Returns whether there are no states left on the stack.
+    pub(crate) fn is_empty(&self) -> bool {
+        self.root != Root::Entered && self.states.is_empty()
+    }
+
+    /// Enters the root state.
+    pub(crate) fn start(
+        &mut self,
+        ctx: &mut Context<G>,
+        root: &mut dyn State<Context = G>,
+    ) -> Result<()> {
+        root.on_enter(ctx)?;
+        self.root = Root::Entered;
+
+        Ok(())
+    }
+
+    /// Updates the states that are let through by the states above them,
+    /// from the bottom up, and then makes the transition returned by the
+    /// state on top.
+    pub(crate) fn update(
+        &mut self,
+        ctx: &mut Context<G>,
+        root: &mut dyn State<Context = G>,
+    ) -> Result<()> {
+        let mut states = self.live(root);
+        let Some(first) = first_let_through(&states, |state| state.update_below()) else {
+            return Ok(());
+        };
+
+        let top = states.len() - 1;
+        let mut transition = Transition::None;
+        for (index, state) in states.iter_mut().enumerate().skip(first) {
+            let result = state.update(ctx)?;
+            if index == top {
+                transition = result;
+            }
+        }
+
+        self.transition(ctx, root, transition)
+    }
+
+    /// Draws the states that are let through by the states above them, from
+    /// the bottom up.
+    pub(crate) fn draw(
+        &mut self,
+        ctx: &mut Context<G>,
+        root: &mut dyn State<Context = G>,
+        dt: f64,
+    ) -> Result<()> {
+        let mut states = self.live(root);
+        let Some(first) = first_let_through(&states, |state| state.draw_below()) else {
+            return Ok(());
+        };
+
+        for state in states.iter_mut().skip(first) {
+            state.draw(ctx, dt)?;
+        }
+
+        Ok(())
+    }
+
+    /// Exits all of the states, from the top down.
+    pub(crate) fn clear(
+        &mut self,
+        ctx: &mut Context<G>,
+        root: &mut dyn State<Context = G>,
+    ) -> Result<()> {
+        while !self.is_empty() {
+            self.exit_top(ctx, root)?;
+        }
+
+        Ok(())
+    }
+
+    fn transition(
+        &mut self,
+        ctx: &mut Context<G>,
+        root: &mut dyn State<Context = G>,
+        transition: Transition<G>,
+    ) -> Result<()> {
+        match transition {
+            Transition::None => {}
+            Transition::Push(mut state) => {
+                if let Some(top) = self.live(root).last_mut() {
+                    top.on_pause(ctx)?;
+                }
+
+                state.on_enter(ctx)?;
+                self.states.push(state);
+            }
+            Transition::Pop => {
+                self.exit_top(ctx, root)?;
+
+                if let Some(top) = self.live(root).last_mut() {
+                    top.on_resume(ctx)?;
+                }
+            }
+            Transition::Switch(mut state) => {
+                self.exit_top(ctx, root)?;
+
+                state.on_enter(ctx)?;
+                self.states.push(state);
+            }
+            Transition::Quit => self.clear(ctx, root)?,
+        }
+
+        Ok(())
+    }
+
+    /// Exits the state on top of the stack and removes it.
+    fn exit_top(&mut self, ctx: &mut Context<G>, root: &mut dyn State<Context = G>) -> Result<()> {
+        if let Some(mut state) = self.states.pop() {
+            state.on_exit(ctx)?;
+        } else if self.root == Root::Entered {
+            root.on_exit(ctx)?;
+            self.root = Root::Exited;
+        }
+
+        Ok(())
+    }
+
+    /// Returns the states on the stack, from the bottom up.
+    fn live<'s>(
+        &'s mut self,
+        root: &'s mut dyn State<Context = G>,
+    ) -> Vec<&'s mut dyn State<Context = G>> {
+        let root = Some(root).filter(|_| self.root == Root::Entered);
+
+        root.into_iter()
+            .chain(self.states.iter_mut().map(|state| state.as_mut() as _))
+            .collect()
+    }
+}
+
+/// Returns the index of the lowest state that is reached by walking down from
+/// the top of the stack while `let_through` holds.
+fn first_let_through<G>(
+    states: &[&mut dyn State<Context = G>],
+    let_through: impl Fn(&dyn State<Context = G>) -> bool,
+) -> Option<usize> {
+    let top = states.len().checked_sub(1)?;
+
+    let mut first = top;
+    while first > 0 && let_through(&*states[first]) {
+        first -= 1;
+    }
+
+    Some(first)
+}
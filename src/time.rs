@@ -1,6 +1,208 @@
 use std::time::Duration;
 
-use crate::Context;
+use crate::error::TimeError;
+use crate::{Context, Result};
+
+/// The default maximum number of fixed updates that are run in a single frame.
+pub const DEFAULT_MAX_UPDATES_PER_FRAME: u32 = 5;
+
+/// How the game loop advances the game between frames.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Timestep {
+    /// The game is advanced in fixed updates of the tick rate, running as many
+    /// as it takes to catch up with the time that has passed.
+    Fixed,
+
+    /// The game is advanced by a single update per frame, using the time that
+    /// passed since the last frame.
+    Variable,
+}
+
+/// The state of the game loop's clock.
+pub(crate) struct TimeContext {
+    tick_rate: Duration,
+    target_frame_rate: Option<f64>,
+    max_updates_per_frame: u32,
+    pub(crate) timestep: Timestep,
+    time_scale: f64,
+    is_paused: bool,
+
+    /// Whether a single update was requested while the game is paused.
+    is_single_stepping: bool,
+
+    /// The time that has passed but has not been simulated yet.
+    lag: Duration,
+
+    /// The time that the current update advances the game by.
+    delta: Duration,
+
+    total_time: Duration,
+    tick_count: u64,
+    frame_count: u64,
+}
+
+impl TimeContext {
+    pub(crate) fn new(tick_rate: Duration) -> Self {
+        Self {
+            tick_rate,
+            target_frame_rate: None,
+            max_updates_per_frame: DEFAULT_MAX_UPDATES_PER_FRAME,
+            timestep: Timestep::Fixed,
+            time_scale: 1.0,
+            is_paused: false,
+            is_single_stepping: false,
+            lag: Duration::ZERO,
+            delta: tick_rate,
+            total_time: Duration::ZERO,
+            tick_count: 0,
+            frame_count: 0,
+        }
+    }
+
+    pub(crate) fn set_tick_rate(&mut self, tick_rate: f64) -> Result<(), TimeError> {
+        self.tick_rate = tick_rate_to_duration(tick_rate)?;
+        Ok(())
+    }
+
+    pub(crate) fn set_target_frame_rate(
+        &mut self,
+        target_frame_rate: Option<f64>,
+    ) -> Result<(), TimeError> {
+        if let Some(target_frame_rate) = target_frame_rate {
+            if !target_frame_rate.is_finite() || target_frame_rate <= 0.0 {
+                return Err(TimeError::InvalidFrameRate(target_frame_rate));
+            }
+        }
+
+        self.target_frame_rate = target_frame_rate;
+        Ok(())
+    }
+
+    pub(crate) fn set_time_scale(&mut self, time_scale: f64) -> Result<(), TimeError> {
+        if !time_scale.is_finite() || time_scale < 0.0 {
+            return Err(TimeError::InvalidTimeScale(time_scale));
+        }
+
+        self.time_scale = time_scale;
+        Ok(())
+    }
+
+    pub(crate) fn set_max_updates_per_frame(
+        &mut self,
+        max_updates_per_frame: u32,
+    ) -> Result<(), TimeError> {
+        if max_updates_per_frame == 0 {
+            return Err(TimeError::NoUpdatesPerFrame);
+        }
+
+        self.max_updates_per_frame = max_updates_per_frame;
+        Ok(())
+    }
+
+    /// Starts a new frame after `elapsed` time has passed since the last one,
+    /// returning the number of updates to run during the frame.
+    ///
+    /// Any lag beyond what the maximum number of updates can catch up on is
+    /// dropped, so that one slow frame does not make the following ones slower.
+    /// No time passes while the game is paused, and a variable timestep runs no
+    /// updates in a frame where no game time passed, such as with a time scale
+    /// of `0.0`.
+    pub(crate) fn start_frame(&mut self, elapsed: Duration) -> u32 {
+        if self.is_paused {
+            if self.is_single_stepping {
+                self.is_single_stepping = false;
+                self.delta = self.tick_rate;
+                return 1;
+            }
+
+            return 0;
+        }
+
+        let elapsed = elapsed.mul_f64(self.time_scale);
+
+        match self.timestep {
+            Timestep::Fixed => {
+                self.lag += elapsed;
+                self.delta = self.tick_rate;
+
+                let mut updates = 0;
+                while self.lag >= self.tick_rate && updates < self.max_updates_per_frame {
+                    self.lag -= self.tick_rate;
+                    updates += 1;
+                }
+
+                if self.lag >= self.tick_rate {
+                    self.lag = Duration::ZERO;
+                }
+
+                updates
+            }
+            Timestep::Variable => {
+                self.lag = Duration::ZERO;
+                if elapsed.is_zero() {
+                    return 0;
+                }
+
+                self.delta = elapsed.min(self.tick_rate.saturating_mul(self.max_updates_per_frame));
+
+                1
+            }
+        }
+    }
+
+    /// Starts a single fixed update that is not driven by the clock, such as
+    /// one run by [`Context::step`].
+    pub(crate) fn start_step(&mut self) {
+        self.delta = self.tick_rate;
+    }
+
+    /// Returns the time that the current update advances the game by.
+    pub(crate) fn delta(&self) -> Duration {
+        self.delta
+    }
+
+    /// Records that an update has advanced the game by the current delta.
+    pub(crate) fn finish_update(&mut self) {
+        self.total_time += self.delta;
+        self.tick_count += 1;
+    }
+
+    /// Records that a frame has been drawn.
+    pub(crate) fn finish_frame(&mut self) {
+        self.frame_count += 1;
+    }
+
+    /// Returns how far the game is between the last update and the next one,
+    /// from `0.0` to `1.0`.
+    pub(crate) fn alpha(&self) -> f64 {
+        duration_to_f64(self.lag) / duration_to_f64(self.tick_rate)
+    }
+
+    /// Returns how long to sleep for at the end of a frame that took
+    /// `frame_time`, in order to keep to the target frame rate.
+    pub(crate) fn time_left_in_frame(&self, frame_time: Duration) -> Option<Duration> {
+        let target_frame_rate = self.target_frame_rate?;
+
+        f64_to_duration(1.0 / target_frame_rate)
+            .checked_sub(frame_time)
+            .filter(|time_left| !time_left.is_zero())
+    }
+}
+
+/// Converts a tick rate in seconds to the time between fixed updates,
+/// rejecting rates that are too short to advance the game at all.
+pub(crate) fn tick_rate_to_duration(tick_rate: f64) -> Result<Duration, TimeError> {
+    if !tick_rate.is_finite() || tick_rate <= 0.0 {
+        return Err(TimeError::InvalidTickRate(tick_rate));
+    }
+
+    let tick_rate_duration = f64_to_duration(tick_rate);
+    if tick_rate_duration.is_zero() {
+        return Err(TimeError::InvalidTickRate(tick_rate));
+    }
+
+    Ok(tick_rate_duration)
+}
 
 pub fn duration_to_f64(duration: Duration) -> f64 {
     let seconds = duration.as_secs() as f64;
@@ -18,3 +220,279 @@ pub fn f64_to_duration(duration: f64) -> Duration {
 pub fn get_fps<G>(ctx: &Context<G>) -> f64 {
     ctx.fps_tracker.fps()
 }
+
+/// Returns the time, in seconds, that the current update advances the game by.
+///
+/// This is the tick rate when using a [`Timestep::Fixed`] timestep, and the
+/// time since the last frame when using a [`Timestep::Variable`] timestep.
+pub fn delta<G>(ctx: &Context<G>) -> f64 {
+    duration_to_f64(ctx.time.delta)
+}
+
+/// Returns the time, in seconds, between fixed updates.
+pub fn tick_rate<G>(ctx: &Context<G>) -> f64 {
+    duration_to_f64(ctx.time.tick_rate)
+}
+
+/// Sets the time, in seconds, between fixed updates.
+///
+/// Returns an error if the tick rate is not a positive, finite number of
+/// seconds of at least a nanosecond.
+pub fn set_tick_rate<G>(ctx: &mut Context<G>, tick_rate: f64) -> Result<()> {
+    Ok(ctx.time.set_tick_rate(tick_rate)?)
+}
+
+/// Returns the number of frames per second that the game loop is limited to,
+/// or `None` if it is not limited.
+pub fn target_frame_rate<G>(ctx: &Context<G>) -> Option<f64> {
+    ctx.time.target_frame_rate
+}
+
+/// Limits the game loop to the given number of frames per second, sleeping
+/// for the rest of each frame, or removes the limit if it is `None`.
+///
+/// Returns an error if the frame rate is not a positive, finite number.
+pub fn set_target_frame_rate<G>(
+    ctx: &mut Context<G>,
+    target_frame_rate: Option<f64>,
+) -> Result<()> {
+    Ok(ctx.time.set_target_frame_rate(target_frame_rate)?)
+}
+
+/// Returns the maximum number of fixed updates that are run in a single frame.
+pub fn max_updates_per_frame<G>(ctx: &Context<G>) -> u32 {
+    ctx.time.max_updates_per_frame
+}
+
+/// Sets the maximum number of fixed updates that are run in a single frame.
+///
+/// When the game falls further behind than this, the rest of the lag is
+/// dropped. With a [`Timestep::Variable`] timestep, this limits the time that
+/// a single update can advance the game by to as many ticks.
+///
+/// Returns an error if the maximum is zero, since no updates would ever run.
+pub fn set_max_updates_per_frame<G>(
+    ctx: &mut Context<G>,
+    max_updates_per_frame: u32,
+) -> Result<()> {
+    Ok(ctx.time.set_max_updates_per_frame(max_updates_per_frame)?)
+}
+
+/// Returns how the game loop advances the game between frames.
+pub fn timestep<G>(ctx: &Context<G>) -> Timestep {
+    ctx.time.timestep
+}
+
+/// Sets how the game loop advances the game between frames.
+pub fn set_timestep<G>(ctx: &mut Context<G>, timestep: Timestep) {
+    ctx.time.timestep = timestep;
+}
+
+/// Returns the total time, in seconds, that the game has been advanced by.
+///
+/// This is game time rather than real time, so it is affected by the time
+/// scale and does not advance while the game is paused.
+pub fn total_time<G>(ctx: &Context<G>) -> f64 {
+    duration_to_f64(ctx.time.total_time)
+}
+
+/// Returns the number of updates that have been run.
+pub fn tick_count<G>(ctx: &Context<G>) -> u64 {
+    ctx.time.tick_count
+}
+
+/// Returns the number of frames that have been drawn.
+pub fn frame_count<G>(ctx: &Context<G>) -> u64 {
+    ctx.time.frame_count
+}
+
+/// Returns the rate at which game time passes relative to real time.
+pub fn time_scale<G>(ctx: &Context<G>) -> f64 {
+    ctx.time.time_scale
+}
+
+/// Sets the rate at which game time passes relative to real time.
+///
+/// A time scale below `1.0` slows the game down, and one above `1.0` speeds it
+/// up. When speeding up a fixed timestep, the game will not run more than the
+/// [maximum updates per frame](set_max_updates_per_frame). A time scale of
+/// `0.0` stops game time, much like [pausing](pause) the game.
+///
+/// Returns an error if the time scale is negative or not finite.
+pub fn set_time_scale<G>(ctx: &mut Context<G>, time_scale: f64) -> Result<()> {
+    Ok(ctx.time.set_time_scale(time_scale)?)
+}
+
+/// Returns whether the game is paused.
+pub fn is_paused<G>(ctx: &Context<G>) -> bool {
+    ctx.time.is_paused
+}
+
+/// Pauses the game, so that no updates are run until it is resumed.
+///
+/// Frames are still drawn while the game is paused.
+pub fn pause<G>(ctx: &mut Context<G>) {
+    ctx.time.is_paused = true;
+}
+
+/// Resumes the game after it was paused.
+pub fn resume<G>(ctx: &mut Context<G>) {
+    ctx.time.is_paused = false;
+    ctx.time.is_single_stepping = false;
+}
+
+/// Runs exactly one fixed update on the next frame while the game is paused,
+/// for debugging.
+///
+/// Does nothing if the game is not paused.
+pub fn single_step<G>(ctx: &mut Context<G>) {
+    if ctx.time.is_paused {
+        ctx.time.is_single_stepping = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TICK: Duration = Duration::from_millis(10);
+
+    #[test]
+    fn fixed_timestep_catches_up_on_lag() {
+        let mut time = TimeContext::new(TICK);
+
+        assert_eq!(time.start_frame(Duration::from_millis(5)), 0);
+        assert_eq!(time.start_frame(Duration::from_millis(16)), 2);
+        assert_eq!(time.lag, Duration::from_millis(1));
+        assert_eq!(time.delta, TICK);
+    }
+
+    #[test]
+    fn fixed_timestep_drops_lag_beyond_the_max_updates() {
+        let mut time = TimeContext::new(TICK);
+        time.max_updates_per_frame = 3;
+
+        assert_eq!(time.start_frame(Duration::from_millis(95)), 3);
+        assert_eq!(time.lag, Duration::ZERO);
+        assert_eq!(time.start_frame(Duration::from_millis(5)), 0);
+    }
+
+    #[test]
+    fn variable_timestep_uses_the_frame_time() {
+        let mut time = TimeContext::new(TICK);
+        time.timestep = Timestep::Variable;
+
+        assert_eq!(time.start_frame(Duration::from_millis(7)), 1);
+        assert_eq!(time.delta, Duration::from_millis(7));
+
+        assert_eq!(time.start_frame(Duration::from_secs(1)), 1);
+        assert_eq!(time.delta, TICK * DEFAULT_MAX_UPDATES_PER_FRAME);
+    }
+
+    #[test]
+    fn variable_timestep_skips_frames_where_no_game_time_passed() {
+        let mut time = TimeContext::new(TICK);
+        time.timestep = Timestep::Variable;
+
+        time.time_scale = 0.0;
+        assert_eq!(time.start_frame(Duration::from_millis(7)), 0);
+
+        time.time_scale = 0.5;
+        assert_eq!(time.start_frame(Duration::from_millis(8)), 1);
+        assert_eq!(time.delta, Duration::from_millis(4));
+    }
+
+    #[test]
+    fn time_scale_changes_how_much_lag_builds_up() {
+        let mut time = TimeContext::new(TICK);
+
+        time.time_scale = 0.5;
+        assert_eq!(time.start_frame(Duration::from_millis(30)), 1);
+
+        time.time_scale = 2.0;
+        assert_eq!(time.start_frame(Duration::from_millis(20)), 4);
+    }
+
+    #[test]
+    fn time_scales_must_be_finite_and_not_negative() {
+        let mut time = TimeContext::new(TICK);
+
+        for &time_scale in &[-1.0, f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+            assert!(time.set_time_scale(time_scale).is_err(), "{}", time_scale);
+        }
+        assert_eq!(time.time_scale, 1.0);
+
+        assert_eq!(time.set_time_scale(0.0), Ok(()));
+        assert_eq!(time.start_frame(Duration::from_secs(1)), 0);
+    }
+
+    #[test]
+    fn paused_games_only_run_single_steps() {
+        let mut time = TimeContext::new(TICK);
+        time.is_paused = true;
+
+        assert_eq!(time.start_frame(Duration::from_millis(50)), 0);
+
+        time.is_single_stepping = true;
+        assert_eq!(time.start_frame(Duration::from_millis(50)), 1);
+        time.finish_update();
+        assert_eq!(time.start_frame(Duration::from_millis(50)), 0);
+
+        assert_eq!(time.tick_count, 1);
+        assert_eq!(time.total_time, TICK);
+    }
+
+    #[test]
+    fn time_left_in_frame_keeps_to_the_target_frame_rate() {
+        let mut time = TimeContext::new(TICK);
+        assert_eq!(time.time_left_in_frame(Duration::from_millis(1)), None);
+
+        time.target_frame_rate = Some(50.0);
+        assert_eq!(
+            time.time_left_in_frame(Duration::from_millis(5)),
+            Some(Duration::from_millis(15))
+        );
+        assert_eq!(time.time_left_in_frame(Duration::from_millis(25)), None);
+    }
+
+    #[test]
+    fn tick_rates_must_advance_the_game() {
+        assert_eq!(tick_rate_to_duration(0.01), Ok(TICK));
+        assert_eq!(tick_rate_to_duration(1e-9), Ok(Duration::from_nanos(1)));
+
+        for &tick_rate in &[0.0, -0.01, 1e-10, f64::NAN, f64::INFINITY] {
+            assert!(tick_rate_to_duration(tick_rate).is_err(), "{}", tick_rate);
+        }
+
+        let mut time = TimeContext::new(TICK);
+        assert!(time.set_tick_rate(0.0).is_err());
+        assert_eq!(time.tick_rate, TICK);
+    }
+
+    #[test]
+    fn frame_rates_and_max_updates_are_validated() {
+        let mut time = TimeContext::new(TICK);
+
+        for &frame_rate in &[0.0, -60.0, f64::NAN, f64::INFINITY] {
+            assert!(
+                time.set_target_frame_rate(Some(frame_rate)).is_err(),
+                "{}",
+                frame_rate
+            );
+        }
+        assert_eq!(time.target_frame_rate, None);
+        assert_eq!(time.set_target_frame_rate(Some(60.0)), Ok(()));
+        assert_eq!(time.set_target_frame_rate(None), Ok(()));
+
+        assert_eq!(
+            time.set_max_updates_per_frame(0),
+            Err(TimeError::NoUpdatesPerFrame)
+        );
+        assert_eq!(time.max_updates_per_frame, DEFAULT_MAX_UPDATES_PER_FRAME);
+        assert_eq!(time.set_max_updates_per_frame(1), Ok(()));
+
+        time.timestep = Timestep::Variable;
+        assert_eq!(time.start_frame(Duration::from_secs(1)), 1);
+        assert_eq!(time.delta, TICK);
+    }
+}
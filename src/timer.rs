@@ -0,0 +1,378 @@
+//! Timers that fire after a number of updates or an amount of game time.
+//!
+//! Timers are advanced once per fixed update, before any of the systems or
+//! states are updated, so they respect the [time scale](crate::time::set_time_scale)
+//! and do not advance while the game is [paused](crate::time::pause).
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use crate::error::TimerError;
+use crate::time;
+use crate::{Context, Result};
+
+/// A callback that is run each time a [`Timer`] fires.
+pub type TimerCallback<G> = Box<dyn FnMut(&mut Context<G>) -> Result<()>>;
+
+/// How long a [`Timer`] waits before firing.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Interval {
+    /// A number of fixed updates.
+    ///
+    /// An interval of zero ticks fires every update, like an interval of one.
+    Ticks(u64),
+
+    /// An amount of game time, in seconds, which must be finite and not negative.
+    ///
+    /// An interval of zero seconds fires once every update.
+    Seconds(f64),
+}
+
+/// A handle to a started [`Timer`], used to check on it or cancel it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TimerHandle(u64);
+
+/// A timer that fires once, or repeatedly, after an [`Interval`].
+pub struct Timer<G> {
+    interval: Interval,
+    is_repeating: bool,
+    callback: Option<TimerCallback<G>>,
+}
+
+impl<G> Timer<G> {
+    /// Returns a timer that fires once after the given interval.
+    pub fn once(interval: Interval) -> Self {
+        Self {
+            interval,
+            is_repeating: false,
+            callback: None,
+        }
+    }
+
+    /// Returns a timer that fires every time the given interval passes, until
+    /// it is cancelled.
+    pub fn repeating(interval: Interval) -> Self {
+        Self {
+            is_repeating: true,
+            ..Self::once(interval)
+        }
+    }
+
+    /// Runs the given callback each time the timer fires.
+    pub fn with_callback<F>(mut self, callback: F) -> Self
+    where
+        F: FnMut(&mut Context<G>) -> Result<()> + 'static,
+    {
+        self.callback = Some(Box::new(callback));
+        self
+    }
+}
+
+struct ActiveTimer<G> {
+    timer: Timer<G>,
+    elapsed_ticks: u64,
+    elapsed_time: Duration,
+
+    /// The number of times that the timer fired during the current update.
+    fired: u32,
+    is_finished: bool,
+}
+
+impl<G> ActiveTimer<G> {
+    /// Advances the timer by a single update of `delta`, returning the number
+    /// of times that it fired.
+    fn advance(&mut self, delta: Duration) -> u32 {
+        self.elapsed_ticks += 1;
+        self.elapsed_time += delta;
+
+        let mut fired = 0;
+        loop {
+            let is_due = match self.timer.interval {
+                Interval::Ticks(ticks) => {
+                    let ticks = ticks.max(1);
+                    let is_due = self.elapsed_ticks >= ticks;
+                    if is_due {
+                        self.elapsed_ticks -= ticks;
+                    }
+                    is_due
+                }
+                Interval::Seconds(seconds) => {
+                    let interval = time::f64_to_duration(seconds);
+                    let is_due = self.elapsed_time >= interval;
+                    if is_due {
+                        self.elapsed_time -= interval;
+                    }
+
+                    // An empty interval fires once per update, rather than forever.
+                    is_due && !(interval.is_zero() && fired > 0)
+                }
+            };
+
+            if !is_due {
+                break;
+            }
+
+            fired += 1;
+
+            if !self.timer.is_repeating {
+                self.is_finished = true;
+                break;
+            }
+        }
+
+        fired
+    }
+}
+
+/// The timers that have been started in a [`Context`].
+pub(crate) struct Timers<G> {
+    next_handle: u64,
+    timers: BTreeMap<TimerHandle, ActiveTimer<G>>,
+}
+
+impl<G> Timers<G> {
+    pub(crate) fn new() -> Self {
+        Self {
+            next_handle: 0,
+            timers: BTreeMap::new(),
+        }
+    }
+
+    fn start(&mut self, timer: Timer<G>) -> std::result::Result<TimerHandle, TimerError> {
+        if let Interval::Seconds(seconds) = timer.interval {
+            if !seconds.is_finite() || seconds < 0.0 {
+                return Err(TimerError::InvalidInterval(timer.interval));
+            }
+        }
+
+        let handle = TimerHandle(self.next_handle);
+        self.next_handle += 1;
+
+        self.timers.insert(
+            handle,
+            ActiveTimer {
+                timer,
+                elapsed_ticks: 0,
+                elapsed_time: Duration::ZERO,
+                fired: 0,
+                is_finished: false,
+            },
+        );
+
+        Ok(handle)
+    }
+
+    /// Advances all of the timers by a single update of `delta`, returning the
+    /// timers with callbacks that fired, in the order that they were started.
+    ///
+    /// The timers that finished during the previous update are removed first.
+    pub(crate) fn advance(&mut self, delta: Duration) -> Vec<(TimerHandle, u32)> {
+        self.timers.retain(|_, timer| !timer.is_finished);
+
+        let mut fired_callbacks = Vec::new();
+        for (&handle, timer) in self.timers.iter_mut() {
+            timer.fired = timer.advance(delta);
+
+            if timer.fired > 0 && timer.timer.callback.is_some() {
+                fired_callbacks.push((handle, timer.fired));
+            }
+        }
+
+        fired_callbacks
+    }
+
+    /// Takes the callback out of the given timer, so that it can be run with
+    /// the whole context borrowed.
+    pub(crate) fn take_callback(&mut self, handle: TimerHandle) -> Option<TimerCallback<G>> {
+        self.timers.get_mut(&handle)?.timer.callback.take()
+    }
+
+    /// Puts a callback back into the given timer after it has run, unless the
+    /// timer was cancelled in the meantime.
+    pub(crate) fn restore_callback(&mut self, handle: TimerHandle, callback: TimerCallback<G>) {
+        if let Some(timer) = self.timers.get_mut(&handle) {
+            timer.timer.callback = Some(callback);
+        }
+    }
+
+    fn is_active(&self, handle: TimerHandle) -> bool {
+        self.timers
+            .get(&handle)
+            .is_some_and(|timer| !timer.is_finished)
+    }
+}
+
+impl Timers<()> {
+    /// Moves the timers that were started while a context was being built into
+    /// the timers of the built context, keeping their handles.
+    ///
+    /// Callbacks take a `Context<()>`, which cannot be run against the built
+    /// context, so timers with callbacks are rejected rather than silently
+    /// dropped.
+    pub(crate) fn into_game<G>(self) -> std::result::Result<Timers<G>, TimerError> {
+        let mut timers = BTreeMap::new();
+        for (handle, active) in self.timers {
+            if active.timer.callback.is_some() {
+                return Err(TimerError::SetupCallback(handle));
+            }
+
+            timers.insert(
+                handle,
+                ActiveTimer {
+                    timer: Timer {
+                        interval: active.timer.interval,
+                        is_repeating: active.timer.is_repeating,
+                        callback: None,
+                    },
+                    elapsed_ticks: active.elapsed_ticks,
+                    elapsed_time: active.elapsed_time,
+                    fired: active.fired,
+                    is_finished: active.is_finished,
+                },
+            );
+        }
+
+        Ok(Timers {
+            next_handle: self.next_handle,
+            timers,
+        })
+    }
+}
+
+/// Starts a timer, returning a handle to it.
+///
+/// Timers with callbacks cannot be started while the context is being built,
+/// since the callbacks would not have access to the game context.
+///
+/// Returns an error if the timer's interval is a negative, infinite or NaN
+/// number of seconds.
+pub fn start<G>(
+    ctx: &mut Context<G>,
+    timer: Timer<G>,
+) -> std::result::Result<TimerHandle, TimerError> {
+    ctx.timers.start(timer)
+}
+
+/// Cancels a timer, so that it does not fire again.
+///
+/// Returns whether the timer was still active.
+pub fn cancel<G>(ctx: &mut Context<G>, handle: TimerHandle) -> bool {
+    let is_active = ctx.timers.is_active(handle);
+    ctx.timers.timers.remove(&handle);
+    is_active
+}
+
+/// Returns whether a timer is still waiting to fire again.
+///
+/// A one-shot timer stops being active once it has fired.
+pub fn is_active<G>(ctx: &Context<G>, handle: TimerHandle) -> bool {
+    ctx.timers.is_active(handle)
+}
+
+/// Returns the number of times that a timer fired during the current update,
+/// for polling timers that do not have a callback.
+pub fn fired<G>(ctx: &Context<G>, handle: TimerHandle) -> u32 {
+    ctx.timers
+        .timers
+        .get(&handle)
+        .map_or(0, |timer| timer.fired)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TICK: Duration = Duration::from_millis(10);
+
+    fn fired_per_update(timers: &mut Timers<()>, handle: TimerHandle, updates: u32) -> Vec<u32> {
+        (0..updates)
+            .map(|_| {
+                timers.advance(TICK);
+                timers.timers.get(&handle).map_or(0, |timer| timer.fired)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn one_shot_timers_fire_once_and_are_removed() {
+        let mut timers = Timers::<()>::new();
+        let handle = timers.start(Timer::once(Interval::Ticks(2))).unwrap();
+
+        assert_eq!(fired_per_update(&mut timers, handle, 2), vec![0, 1]);
+        assert!(!timers.is_active(handle));
+
+        assert_eq!(fired_per_update(&mut timers, handle, 1), vec![0]);
+        assert!(timers.timers.is_empty());
+    }
+
+    #[test]
+    fn repeating_timers_fire_every_interval() {
+        let mut timers = Timers::<()>::new();
+        let ticks = timers.start(Timer::repeating(Interval::Ticks(2))).unwrap();
+        let seconds = timers
+            .start(Timer::repeating(Interval::Seconds(0.005)))
+            .unwrap();
+
+        assert_eq!(fired_per_update(&mut timers, ticks, 4), vec![0, 1, 0, 1]);
+        assert_eq!(timers.timers[&seconds].fired, 2);
+        assert!(timers.is_active(ticks));
+    }
+
+    #[test]
+    fn invalid_intervals_are_rejected() {
+        let mut timers = Timers::<()>::new();
+
+        for seconds in [-1.0, f64::NAN, f64::INFINITY] {
+            assert!(matches!(
+                timers.start(Timer::once(Interval::Seconds(seconds))),
+                Err(TimerError::InvalidInterval(Interval::Seconds(_)))
+            ));
+        }
+        assert!(timers.timers.is_empty());
+
+        let zero_seconds = timers
+            .start(Timer::repeating(Interval::Seconds(0.0)))
+            .unwrap();
+        let zero_ticks = timers.start(Timer::repeating(Interval::Ticks(0))).unwrap();
+        assert_eq!(fired_per_update(&mut timers, zero_seconds, 2), vec![1, 1]);
+        assert_eq!(timers.timers[&zero_ticks].fired, 1);
+    }
+
+    #[test]
+    fn only_timers_with_callbacks_are_returned() {
+        let mut timers = Timers::<()>::new();
+        timers.start(Timer::once(Interval::Ticks(1))).unwrap();
+        let callback = timers
+            .start(Timer::once(Interval::Ticks(1)).with_callback(|_| Ok(())))
+            .unwrap();
+
+        assert_eq!(timers.advance(TICK), vec![(callback, 1)]);
+
+        let taken = timers.take_callback(callback).unwrap();
+        timers.timers.clear();
+        timers.restore_callback(callback, taken);
+        assert!(timers.timers.is_empty());
+    }
+
+    #[test]
+    fn into_game_keeps_timers_without_callbacks() {
+        let mut timers = Timers::<()>::new();
+        let polled = timers.start(Timer::repeating(Interval::Ticks(1))).unwrap();
+
+        let mut game_timers = timers.into_game::<u32>().unwrap();
+        assert!(game_timers.is_active(polled));
+        assert_ne!(
+            game_timers.start(Timer::once(Interval::Ticks(1))).unwrap(),
+            polled
+        );
+
+        let mut timers = Timers::<()>::new();
+        let callback = timers
+            .start(Timer::once(Interval::Ticks(1)).with_callback(|_| Ok(())))
+            .unwrap();
+        assert!(matches!(
+            timers.into_game::<u32>(),
+            Err(TimerError::SetupCallback(handle)) if handle == callback
+        ));
+    }
+}
@@ -1,7 +1,11 @@
 use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign};
 
+use serde::{Deserialize, Serialize};
+
 /// A two-dimensional vector.
-#[derive(Clone, PartialOrd, Ord, PartialEq, Eq, Debug, Copy, Default, Hash)]
+#[derive(
+    Clone, PartialOrd, Ord, PartialEq, Eq, Debug, Copy, Default, Hash, Serialize, Deserialize,
+)]
 pub struct Vector2<T> {
     /// The x-component of the vector.
     pub x: T,
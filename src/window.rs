@@ -1,14 +1,118 @@
 use sdl2::rect::Rect as SdlRect;
+use sdl2::video::{FullscreenType, WindowPos};
 
+use crate::error::{AnyhowContext, Sdl2Error};
 use crate::graphics::View;
-use crate::Context;
+use crate::{Context, Result, Vector2i, Vector2u};
+
+/// The fullscreen mode of the window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fullscreen {
+    /// The window is not fullscreen.
+    Off,
+
+    /// The window is fullscreen, changing the video mode of the display to
+    /// match the size of the window.
+    Exclusive,
+
+    /// The window is fullscreen at the current resolution of the desktop.
+    Desktop,
+}
+
+impl From<Fullscreen> for FullscreenType {
+    fn from(fullscreen: Fullscreen) -> Self {
+        match fullscreen {
+            Fullscreen::Off => FullscreenType::Off,
+            Fullscreen::Exclusive => FullscreenType::True,
+            Fullscreen::Desktop => FullscreenType::Desktop,
+        }
+    }
+}
+
+impl From<FullscreenType> for Fullscreen {
+    fn from(fullscreen: FullscreenType) -> Self {
+        match fullscreen {
+            FullscreenType::Off => Fullscreen::Off,
+            FullscreenType::True => Fullscreen::Exclusive,
+            FullscreenType::Desktop => Fullscreen::Desktop,
+        }
+    }
+}
 
 /// Sets the title of the window.
+///
+/// Does nothing if the context is [headless](crate::ContextBuilder::headless).
 pub fn set_title<G>(ctx: &mut Context<G>, title: &str) {
+    if let Some(window) = ctx.canvas.window_mut() {
+        window.set_title(title).expect("Failed to set window title");
+    }
+}
+
+/// Returns the fullscreen mode of the window.
+///
+/// A [headless](crate::ContextBuilder::headless) context is never fullscreen.
+pub fn fullscreen<G>(ctx: &Context<G>) -> Fullscreen {
     ctx.canvas
-        .window_mut()
-        .set_title(title)
-        .expect("Failed to set window title");
+        .window()
+        .map_or(Fullscreen::Off, |window| window.fullscreen_state().into())
+}
+
+/// Sets the fullscreen mode of the window.
+///
+/// Does nothing if the context is [headless](crate::ContextBuilder::headless).
+pub fn set_fullscreen<G>(ctx: &mut Context<G>, fullscreen: Fullscreen) -> Result<()> {
+    if let Some(window) = ctx.canvas.window_mut() {
+        window
+            .set_fullscreen(fullscreen.into())
+            .map_err(Sdl2Error::ErrorMessage)
+            .context("Failed to set window fullscreen mode")?;
+    }
+
+    Ok(())
+}
+
+/// Returns the size of the window.
+///
+/// For a [headless](crate::ContextBuilder::headless) context, this is the
+/// size of the offscreen surface that is rendered to.
+pub fn size<G>(ctx: &Context<G>) -> Vector2u {
+    ctx.canvas.size().into()
+}
+
+/// Sets the size of the window.
+///
+/// Does nothing if the context is [headless](crate::ContextBuilder::headless).
+pub fn set_size<G>(ctx: &mut Context<G>, size: Vector2u) -> Result<()> {
+    if let Some(window) = ctx.canvas.window_mut() {
+        window
+            .set_size(size.x, size.y)
+            .context("Failed to set window size")?;
+    }
+
+    Ok(())
+}
+
+/// Returns the position of the window on the screen.
+///
+/// A [headless](crate::ContextBuilder::headless) context is always at the
+/// origin.
+pub fn position<G>(ctx: &Context<G>) -> Vector2i {
+    ctx.canvas
+        .window()
+        .map_or((0, 0), |window| window.position())
+        .into()
+}
+
+/// Sets the position of the window on the screen.
+///
+/// Does nothing if the context is [headless](crate::ContextBuilder::headless).
+pub fn set_position<G>(ctx: &mut Context<G>, position: Vector2i) {
+    if let Some(window) = ctx.canvas.window_mut() {
+        window.set_position(
+            WindowPos::Positioned(position.x),
+            WindowPos::Positioned(position.y),
+        );
+    }
 }
 
 /// Sets a new view for the window.
@@ -0,0 +1,159 @@
+//! Drives a headless context frame by frame, without a display.
+
+use peacock::graphics::{self, Color, DrawImageParams, Image};
+use peacock::timer::{self, Interval, Timer};
+use peacock::{time, ContextBuilder, Result, State, Transition, Vector2f};
+
+/// The events that the states have seen, in order.
+type Log = Vec<&'static str>;
+
+type Context = peacock::Context<Log>;
+
+struct Level {
+    updates: u32,
+    image: Image,
+}
+
+impl State for Level {
+    type Context = Log;
+
+    fn on_enter(&mut self, ctx: &mut Context) -> Result<()> {
+        ctx.game_mut().push("level entered");
+        Ok(())
+    }
+
+    fn on_pause(&mut self, ctx: &mut Context) -> Result<()> {
+        ctx.game_mut().push("level paused");
+        Ok(())
+    }
+
+    fn on_resume(&mut self, ctx: &mut Context) -> Result<()> {
+        ctx.game_mut().push("level resumed");
+        Ok(())
+    }
+
+    fn update(&mut self, _ctx: &mut Context) -> Result<Transition<Log>> {
+        self.updates += 1;
+
+        if self.updates == 2 {
+            Ok(Transition::push(Pause))
+        } else if self.updates == 4 {
+            Ok(Transition::Quit)
+        } else {
+            Ok(Transition::None)
+        }
+    }
+
+    fn draw(&mut self, ctx: &mut Context, _dt: f64) -> Result<()> {
+        ctx.game_mut().push("level drawn");
+        graphics::draw(
+            ctx,
+            &self.image,
+            &DrawImageParams {
+                position: Vector2f::new(8.0, 8.0),
+                ..Default::default()
+            },
+        )
+    }
+}
+
+struct Pause;
+
+impl State for Pause {
+    type Context = Log;
+
+    fn on_exit(&mut self, ctx: &mut Context) -> Result<()> {
+        ctx.game_mut().push("pause exited");
+        Ok(())
+    }
+
+    fn update(&mut self, _ctx: &mut Context) -> Result<Transition<Log>> {
+        Ok(Transition::Pop)
+    }
+
+    fn draw(&mut self, ctx: &mut Context, _dt: f64) -> Result<()> {
+        ctx.game_mut().push("pause drawn");
+        Ok(())
+    }
+
+    fn draw_below(&self) -> bool {
+        true
+    }
+}
+
+#[test]
+fn step_advances_the_game_frame_by_frame() -> Result<()> {
+    let mut ctx = ContextBuilder::new("headless", 64, 64)
+        .headless()
+        .build(|_| Ok(Log::new()))?;
+
+    let image = Image::from_color(&mut ctx, (16, 16).into(), Color::RED)?;
+    let mut level = Level { updates: 0, image };
+
+    ctx.step(&mut level, 2, true)?;
+    assert_eq!(level.updates, 2);
+    assert_eq!(
+        ctx.game(),
+        &[
+            "level entered",
+            "level paused",
+            "level drawn",
+            "pause drawn"
+        ]
+    );
+
+    // The pause state pops itself instead of letting the level update.
+    ctx.game_mut().clear();
+    ctx.step(&mut level, 1, false)?;
+    assert_eq!(level.updates, 2);
+    assert_eq!(ctx.game(), &["pause exited", "level resumed"]);
+
+    // The level quits on its fourth update, after which nothing is updated.
+    ctx.step(&mut level, 5, true)?;
+    assert_eq!(level.updates, 4);
+    assert_eq!(ctx.game(), &["pause exited", "level resumed"]);
+
+    Ok(())
+}
+
+struct Idle;
+
+impl State for Idle {
+    type Context = Log;
+
+    fn update(&mut self, _ctx: &mut Context) -> Result<Transition<Log>> {
+        Ok(Transition::None)
+    }
+
+    fn draw(&mut self, _ctx: &mut Context, _dt: f64) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn timers_fire_during_fixed_updates() -> Result<()> {
+    let mut ctx = ContextBuilder::new("headless", 64, 64)
+        .headless()
+        .build(|_| Ok(Log::new()))?;
+
+    let repeating = timer::start(
+        &mut ctx,
+        Timer::repeating(Interval::Ticks(2)).with_callback(|ctx: &mut Context| {
+            ctx.game_mut().push("tick");
+            Ok(())
+        }),
+    )?;
+    let once = timer::start(&mut ctx, Timer::once(Interval::Ticks(3)))?;
+
+    ctx.step(&mut Idle, 3, false)?;
+    assert_eq!(time::tick_count(&ctx), 3);
+    assert_eq!(ctx.game(), &["tick"]);
+    assert_eq!(timer::fired(&ctx, once), 1);
+    assert!(!timer::is_active(&ctx, once));
+
+    assert!(timer::cancel(&mut ctx, repeating));
+    ctx.step(&mut Idle, 4, false)?;
+    assert_eq!(ctx.game(), &["tick"]);
+
+    Ok(())
+}